@@ -0,0 +1,565 @@
+// crates/cli/src/config_command.rs
+
+use anyhow::{anyhow, Context};
+use clap::{Subcommand, ValueEnum};
+use config::{
+    args::ArgsConfigurationProvider,
+    diagnostic::{diagnose, Diagnostic},
+    diff::{diff_configs, ConfigChange},
+    directory::DirectoryConfigurationProvider,
+    env::EnvConfigurationProvider,
+    format::ConfigFormat,
+    layers::LayeredConfigurationProvider,
+    loader::FileConfigurationProvider,
+    migration::MigrationRegistry,
+    render::{render, render_with_sources},
+    schema::app_config_schema,
+    secret::{SecretKey, SECRET_KEY_ENV, SECRET_KEY_FILE_ENV},
+    traits::ConfigurationProvider,
+    validation::{NoTraceInProduction, Validator},
+    writer::ConfigWriter,
+};
+use std::{
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// The file loaded when no `--config` option is given.
+const DEFAULT_CONFIG_FILE: &str = "ciphr.toml";
+
+#[derive(Subcommand)]
+pub enum ConfigCommand {
+    /// Shows the value of a field and which layer set it.
+    Explain {
+        /// The dotted path of the field, e.g. `log_level` or
+        /// `feature_flags.new_ui`.
+        field: String,
+    },
+    /// Prints the merged configuration. Secrets are redacted.
+    Show {
+        /// The output format.
+        #[arg(long, value_enum, default_value_t = OutputFormat::Toml)]
+        format: OutputFormat,
+        /// Annotate each value with the layer that set it (TOML only).
+        #[arg(long)]
+        sources: bool,
+    },
+    /// Loads and validates the configuration, and points at each problem
+    /// in the files. Exits with status 1 if there are any.
+    Check,
+    /// Sets a field in the last `--config` file, keeping its comments and
+    /// formatting.
+    Set {
+        /// The dotted path of the field, e.g. `log_level`.
+        field: String,
+        /// The new value, e.g. `debug`, `true` or `["a", "b"]`.
+        value: String,
+    },
+    /// Compares two configuration files and prints the fields that differ.
+    Diff {
+        /// The file to compare from, e.g. `staging.toml`.
+        old: PathBuf,
+        /// The file to compare to, e.g. `production.toml`.
+        new: PathBuf,
+        /// The output format.
+        #[arg(long, value_enum, default_value_t = DiffFormat::Text)]
+        format: DiffFormat,
+        /// Exit with status 1 if the files differ, e.g. to detect drift in CI.
+        #[arg(long)]
+        exit_code: bool,
+    },
+    /// Upgrades the `--config` files to the latest `config_version`, keeping
+    /// their comments and formatting.
+    Migrate {
+        /// Report what would change without writing the files.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Prints the JSON Schema of the configuration file, for editor
+    /// completion and validation.
+    Schema {
+        /// Write the schema to this file instead of standard output.
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Prints a new random key for encrypting secrets.
+    GenerateKey,
+    /// Encrypts a value for use in the `[secrets]` table.
+    Encrypt {
+        /// The value to encrypt. Read from standard input if omitted, so
+        /// that it does not end up in the shell history.
+        value: Option<String>,
+        /// File containing the key. Defaults to `CIPHR_SECRET_KEY` or
+        /// `CIPHR_SECRET_KEY_FILE`.
+        #[arg(long, value_name = "FILE")]
+        key_file: Option<PathBuf>,
+    },
+}
+
+/// The formats `config show` can print.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OutputFormat {
+    Toml,
+    Json,
+    Yaml,
+}
+
+impl From<OutputFormat> for ConfigFormat {
+    fn from(format: OutputFormat) -> Self {
+        match format {
+            OutputFormat::Toml => ConfigFormat::Toml,
+            OutputFormat::Json => ConfigFormat::Json,
+            OutputFormat::Yaml => ConfigFormat::Yaml,
+        }
+    }
+}
+
+/// The formats `config diff` can print.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum DiffFormat {
+    /// One line per change, e.g. `~ log_level: info -> debug`.
+    Text,
+    /// A JSON array of changes.
+    Json,
+}
+
+/// Returns the files to load, in order of increasing precedence.
+fn config_files(config_files: &[PathBuf]) -> Vec<PathBuf> {
+    if config_files.is_empty() {
+        vec![PathBuf::from(DEFAULT_CONFIG_FILE)]
+    } else {
+        config_files.to_vec()
+    }
+}
+
+/// Returns the migrations applied when loading and by `config migrate`.
+///
+/// This is the registry for the built-in fields; crates whose sections need
+/// migrating add their `Migration`s here, in version order.
+fn migrations() -> MigrationRegistry {
+    MigrationRegistry::default()
+}
+
+/// Builds the provider stack used by all `config` subcommands: the given
+/// files in order, followed by `CIPHR_*` environment variables and finally
+/// the `--set` overrides. A directory, such as `conf.d`, adds each of its
+/// files in lexical order.
+///
+/// On top of the default rules, the CLI forbids `trace` logging in
+/// production.
+fn provider(
+    config_files: &[PathBuf],
+    overrides: &[String],
+) -> anyhow::Result<LayeredConfigurationProvider> {
+    Ok(self::config_files(config_files)
+        .into_iter()
+        .fold(LayeredConfigurationProvider::new(), |layered, file| {
+            if file.is_dir() {
+                layered.with_provider(Box::new(
+                    DirectoryConfigurationProvider::new(file).with_migrations(migrations()),
+                ))
+            } else {
+                layered.with_provider(Box::new(
+                    FileConfigurationProvider::new(file).with_migrations(migrations()),
+                ))
+            }
+        })
+        .with_provider(Box::new(EnvConfigurationProvider::new()))
+        .with_overrides(ArgsConfigurationProvider::new(overrides)?)
+        .with_validator(Validator::default().with_rule(NoTraceInProduction)))
+}
+
+/// Describes where `field` got its value, e.g.
+/// `log_level = debug` followed by `set by prod.toml:3, overriding defaults.toml:1`.
+fn explain(provider: &dyn ConfigurationProvider, field: &str) -> anyhow::Result<String> {
+    let (config, provenance) = provider
+        .load_with_provenance()
+        .context("Failed to load configuration")?
+        .ok_or_else(|| anyhow!("No configuration found"))?;
+
+    let value = config
+        .fields()
+        .into_iter()
+        .find_map(|(name, value)| (name == field).then_some(value));
+    match (value, provenance.get(field)) {
+        (Some(value), Some(source)) => Ok(format!("{} = {}\n  {}", field, value, source)),
+        _ => Err(anyhow!("`{}` is not set by any configuration layer", field)),
+    }
+}
+
+/// Renders the merged configuration, optionally annotated with sources.
+fn show(
+    provider: &dyn ConfigurationProvider,
+    format: OutputFormat,
+    sources: bool,
+) -> anyhow::Result<String> {
+    let (config, provenance) = provider
+        .load_with_provenance()
+        .context("Failed to load configuration")?
+        .ok_or_else(|| anyhow!("No configuration found"))?;
+
+    match (format, sources) {
+        (OutputFormat::Toml, true) => Ok(render_with_sources(&config, &provenance)?),
+        (_, true) => Err(anyhow!("--sources is only supported for TOML output")),
+        (format, false) => Ok(render(&config, format.into())?),
+    }
+}
+
+/// Loads and validates the configuration, describing every problem found.
+fn check(provider: &dyn ConfigurationProvider) -> Vec<Diagnostic> {
+    let (config, provenance) = match provider.load_with_provenance() {
+        Ok(Some(loaded)) => loaded,
+        Ok(None) => {
+            return vec![
+                Diagnostic::new("no configuration found").with_help(Some(format!(
+                    "create {} or pass --config",
+                    DEFAULT_CONFIG_FILE
+                ))),
+            ]
+        }
+        Err(e) => return diagnose(&e, None),
+    };
+    match provider.validate(&config) {
+        Ok(()) => Vec::new(),
+        Err(e) => diagnose(&e, Some(&provenance)),
+    }
+}
+
+/// Compares the configurations in two files. Each file is loaded on its
+/// own, without environment variables or other layers.
+fn diff(old: &Path, new: &Path) -> anyhow::Result<Vec<ConfigChange>> {
+    let load = |path: &Path| {
+        FileConfigurationProvider::new(path)
+            .load()
+            .with_context(|| format!("Failed to load {}", path.display()))?
+            .ok_or_else(|| anyhow!("{} does not exist", path.display()))
+    };
+    Ok(diff_configs(&load(old)?, &load(new)?))
+}
+
+/// Renders the changes found by `diff`.
+fn render_diff(changes: &[ConfigChange], format: DiffFormat) -> anyhow::Result<String> {
+    match format {
+        DiffFormat::Text if changes.is_empty() => Ok("No differences\n".to_string()),
+        DiffFormat::Text => Ok(changes
+            .iter()
+            .map(|change| format!("{}\n", change))
+            .collect()),
+        DiffFormat::Json => Ok(serde_json::to_string_pretty(changes)? + "\n"),
+    }
+}
+
+/// Upgrades `file` in place and describes what was done.
+fn migrate(file: &Path, migrations: &MigrationRegistry, dry_run: bool) -> anyhow::Result<String> {
+    if ConfigFormat::from_path(file).unwrap_or(ConfigFormat::Toml) != ConfigFormat::Toml {
+        return Err(anyhow!(
+            "{}: only TOML files can be migrated in place",
+            file.display()
+        ));
+    }
+    let mut writer =
+        ConfigWriter::open(file).with_context(|| format!("Failed to read {}", file.display()))?;
+    let Some(migrated) = writer.migrate(migrations)? else {
+        return Ok(format!("{}: up to date\n", file.display()));
+    };
+    if !dry_run {
+        writer
+            .save()
+            .with_context(|| format!("Failed to update {}", file.display()))?;
+    }
+
+    let mut report = format!(
+        "{}: {} version {} to {}\n",
+        file.display(),
+        if dry_run {
+            "would upgrade from"
+        } else {
+            "upgraded from"
+        },
+        migrated.from,
+        migrated.to
+    );
+    for description in migrated.applied {
+        report.push_str(&format!("  - {}\n", description));
+    }
+    Ok(report)
+}
+
+/// Loads the encryption key from `key_file`, or from the environment.
+fn secret_key(key_file: Option<PathBuf>) -> anyhow::Result<SecretKey> {
+    match key_file {
+        Some(path) => SecretKey::from_file(&path)
+            .with_context(|| format!("Failed to read key from {}", path.display())),
+        None => SecretKey::from_env()?.ok_or_else(|| {
+            anyhow!(
+                "No key given; pass --key-file or set {} or {}",
+                SECRET_KEY_ENV,
+                SECRET_KEY_FILE_ENV
+            )
+        }),
+    }
+}
+
+/// Runs a `config` subcommand.
+pub fn run(
+    command: ConfigCommand,
+    config_files: &[PathBuf],
+    overrides: &[String],
+) -> anyhow::Result<()> {
+    let provider = provider(config_files, overrides)?;
+    match command {
+        ConfigCommand::Explain { field } => println!("{}", explain(&provider, &field)?),
+        ConfigCommand::Show { format, sources } => print!("{}", show(&provider, format, sources)?),
+        ConfigCommand::Check => {
+            let diagnostics = check(&provider);
+            if diagnostics.is_empty() {
+                println!("Configuration is valid");
+            } else {
+                for diagnostic in &diagnostics {
+                    eprintln!("{}", diagnostic);
+                }
+                std::process::exit(1);
+            }
+        }
+        ConfigCommand::Set { field, value } => {
+            let file = self::config_files(config_files)
+                .pop()
+                .expect("there is always at least one config file");
+            let mut writer = ConfigWriter::open(&file)
+                .with_context(|| format!("Failed to read {}", file.display()))?;
+            writer.set(&field, &value)?;
+            writer
+                .save()
+                .with_context(|| format!("Failed to update {}", file.display()))?;
+        }
+        ConfigCommand::Diff {
+            old,
+            new,
+            format,
+            exit_code,
+        } => {
+            let changes = diff(&old, &new)?;
+            print!("{}", render_diff(&changes, format)?);
+            if exit_code && !changes.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        ConfigCommand::Migrate { dry_run } => {
+            let migrations = migrations();
+            for file in self::config_files(config_files) {
+                print!("{}", migrate(&file, &migrations, dry_run)?);
+            }
+        }
+        ConfigCommand::Schema { output } => {
+            let schema = serde_json::to_string_pretty(&app_config_schema())?;
+            match output {
+                Some(path) => std::fs::write(&path, schema + "\n")
+                    .with_context(|| format!("Failed to write {}", path.display()))?,
+                None => println!("{}", schema),
+            }
+        }
+        ConfigCommand::GenerateKey => println!("{}", SecretKey::generate().to_base64()),
+        ConfigCommand::Encrypt { value, key_file } => {
+            let key = secret_key(key_file)?;
+            let value = match value {
+                Some(value) => value,
+                None => {
+                    let mut value = String::new();
+                    std::io::stdin()
+                        .read_to_string(&mut value)
+                        .context("Failed to read value from standard input")?;
+                    value.trim_end_matches(['\r', '\n']).to_string()
+                }
+            };
+            println!("{}", key.encrypt(&value));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_utils::mock_fs::create_temp_config_file;
+
+    #[test]
+    fn test_explain_reports_overrides() {
+        let defaults = create_temp_config_file("log_level = \"info\"\n");
+        let production =
+            create_temp_config_file("environment = \"production\"\nlog_level = \"warn\"\n");
+        let provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(defaults.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(production.path())));
+
+        let explanation = explain(&provider, "log_level").unwrap();
+
+        assert_eq!(
+            explanation,
+            format!(
+                "log_level = warn\n  set by {}:2, overriding {}:1",
+                production.path().display(),
+                defaults.path().display()
+            )
+        );
+        assert!(explain(&provider, "log_format").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_value_loads_with_key_file() {
+        let key_file = create_temp_config_file(&SecretKey::generate().to_base64());
+        let key = secret_key(Some(key_file.path().to_path_buf())).unwrap();
+        let config = create_temp_config_file(&format!(
+            "[secrets]\ndb_password = \"{}\"\n",
+            key.encrypt("pa55")
+        ));
+        let provider = FileConfigurationProvider::new(config.path()).with_secret_key(key);
+
+        let loaded = provider.load().unwrap().unwrap();
+        assert_eq!(loaded.secrets["db_password"].expose(), "pa55");
+
+        let explanation = explain(&provider, "secrets.db_password").unwrap();
+        assert!(explanation.starts_with("secrets.db_password = [REDACTED]\n"));
+    }
+
+    #[test]
+    fn test_show_with_sources() {
+        let file = create_temp_config_file(
+            "environment = \"production\"\n[secrets]\ndb_password = \"pa55\"\n",
+        );
+        let provider = FileConfigurationProvider::new(file.path());
+
+        let shown = show(&provider, OutputFormat::Toml, true).unwrap();
+        assert!(shown.contains(&format!(
+            "environment = \"production\" # {}:1\n",
+            file.path().display()
+        )));
+        assert!(shown.contains("db_password = \"[REDACTED]\""));
+        assert!(show(&provider, OutputFormat::Json, true).is_err());
+    }
+
+    #[test]
+    fn test_diff_files() {
+        let staging = create_temp_config_file(
+            "environment = \"staging\"\nlog_level = \"debug\"\n[feature_flags]\nbeta = true\n",
+        );
+        let production = create_temp_config_file(
+            "environment = \"production\"\nlog_level = \"debug\"\n[feature_flags]\nbeta = false\nnew_ui = true\n",
+        );
+
+        let changes = diff(staging.path(), production.path()).unwrap();
+        assert_eq!(
+            render_diff(&changes, DiffFormat::Text).unwrap(),
+            "~ environment: staging -> production\n\
+             ~ feature_flags.beta: true -> false\n\
+             + feature_flags.new_ui = true\n"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_diff(&changes, DiffFormat::Json).unwrap()).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 3);
+        assert_eq!(json[2]["kind"], "added");
+
+        let same = diff(staging.path(), staging.path()).unwrap();
+        assert_eq!(
+            render_diff(&same, DiffFormat::Text).unwrap(),
+            "No differences\n"
+        );
+    }
+
+    #[test]
+    fn test_migrate_file() {
+        use config::migration::Migration;
+
+        let file = create_temp_config_file("log = \"debug\"\n");
+        let migrations = MigrationRegistry::new().with_migration(
+            Migration::new(1, "rename `log` to `log_level`").rename("log", "log_level"),
+        );
+
+        let report = migrate(file.path(), &migrations, true).unwrap();
+        assert_eq!(
+            report,
+            format!(
+                "{}: would upgrade from version 1 to 2\n  - rename `log` to `log_level`\n",
+                file.path().display()
+            )
+        );
+        assert_eq!(
+            std::fs::read_to_string(file.path()).unwrap(),
+            "log = \"debug\"\n"
+        );
+
+        migrate(file.path(), &migrations, false).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(file.path()).unwrap(),
+            "log_level = \"debug\"\nconfig_version = 2\n"
+        );
+        assert!(migrate(file.path(), &migrations, false)
+            .unwrap()
+            .ends_with(": up to date\n"));
+    }
+
+    #[test]
+    fn test_set_overrides_files() {
+        let file = create_temp_config_file("log_level = \"info\"\n");
+        let provider = provider(
+            &[file.path().to_path_buf()],
+            &["log_level=debug".to_string()],
+        )
+        .unwrap();
+
+        let explanation = explain(&provider, "log_level").unwrap();
+        assert!(explanation.starts_with(
+            "log_level = debug\n  set by command-line argument --set log_level=debug"
+        ));
+        assert!(super::provider(&[], &["log_level".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_config_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("10-base.toml"), "log_level = \"info\"\n").unwrap();
+        std::fs::write(dir.path().join("20-local.toml"), "log_level = \"debug\"\n").unwrap();
+        let provider = provider(&[dir.path().to_path_buf()], &[]).unwrap();
+
+        let explanation = explain(&provider, "log_level").unwrap();
+        assert_eq!(
+            explanation,
+            format!(
+                "log_level = debug\n  set by {}:1, overriding {}:1",
+                dir.path().join("20-local.toml").display(),
+                dir.path().join("10-base.toml").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_check_points_at_problems() {
+        let file = create_temp_config_file("environment = \"production\"\nlog_level = \"trace\"\n");
+        let provider = provider(&[file.path().to_path_buf()], &[]).unwrap();
+
+        let diagnostics = check(&provider);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            format!(
+                "error[no-trace-in-production]: log_level: `trace` logging is not allowed in production\n \
+                 --> {}:2:13\n  \
+                 |\n\
+                 2 | log_level = \"trace\"\n  \
+                 |             ^^^^^^^\n  \
+                 = help: use `debug` or a less verbose level\n",
+                file.path().display()
+            )
+        );
+
+        std::fs::write(file.path(), "log_level = ").unwrap();
+        let diagnostics = check(&provider);
+        assert_eq!(
+            diagnostics[0].origin.as_deref(),
+            Some(file.path().to_str().unwrap())
+        );
+
+        let valid = create_temp_config_file("log_level = \"debug\"\n");
+        assert!(check(&FileConfigurationProvider::new(valid.path())).is_empty());
+    }
+}
@@ -1,5 +1,47 @@
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+mod config_command;
 mod health;
 
-fn main() {
-    println!("Hello, world!");
+use config_command::ConfigCommand;
+
+/// Ciphr - plain text accounting.
+#[derive(Parser)]
+#[command(name = "ciphr", version)]
+struct Cli {
+    /// Configuration file to load, or a directory such as `conf.d` whose
+    /// files are loaded in lexical order. Repeat to add layers; later files
+    /// override earlier ones. Defaults to `ciphr.toml`.
+    #[arg(long = "config", short = 'c', global = true, value_name = "PATH")]
+    config_files: Vec<PathBuf>,
+
+    /// Overrides a configuration field for this run, e.g.
+    /// `--set log_level=debug`. Repeat to set several fields; these take
+    /// precedence over files and environment variables.
+    #[arg(long = "set", global = true, value_name = "FIELD=VALUE")]
+    overrides: Vec<String>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Checks the health of the application.
+    Health,
+    /// Inspects the application configuration.
+    #[command(subcommand)]
+    Config(ConfigCommand),
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Health => {
+            health::check_health().map_err(|_| anyhow::anyhow!("Health check failed"))
+        }
+        Command::Config(command) => config_command::run(command, &cli.config_files, &cli.overrides),
+    }
 }
@@ -1,6 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use config::{
     builder::AppConfigBuilder,
+    handle::ConfigHandle,
     loader::FileConfigurationProvider,
     traits::ConfigurationProvider,
     types::{LogFormat, LogLevel},
@@ -44,5 +45,34 @@ fn bench_config_builder(c: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, bench_config_loading, bench_config_builder);
+fn bench_cached_access(c: &mut Criterion) {
+    let content = r#"
+        environment = "production"
+        log_level = "warn"
+        [feature_flags]
+        new_feature = true
+    "#;
+    let file = create_temp_config_file(content);
+    let provider = FileConfigurationProvider::new(file.path());
+    let handle = ConfigHandle::from_provider(&provider).unwrap();
+
+    let mut group = c.benchmark_group("config_access");
+    group.bench_function("provider_load", |b| {
+        b.iter(|| provider.load().unwrap().unwrap().feature_flags["new_feature"].is_enabled())
+    });
+    group.bench_function("handle_snapshot", |b| {
+        b.iter(|| black_box(&handle).snapshot().feature_flags["new_feature"].is_enabled())
+    });
+    group.bench_function("handle_get", |b| {
+        b.iter(|| black_box(&handle).get().feature_flags["new_feature"].is_enabled())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_config_loading,
+    bench_config_builder,
+    bench_cached_access
+);
 criterion_main!(benches); 
\ No newline at end of file
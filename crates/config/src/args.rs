@@ -0,0 +1,294 @@
+//! Loads configuration overrides given on the command line.
+//!
+//! Each override is a `field=value` pair such as `log_level=debug` or
+//! `feature_flags.beta=true`, usually collected from repeated `--set`
+//! options. Fields are dotted paths, as in `AppConfig::fields`, and values
+//! are read like in `ConfigWriter::set`: as a TOML value if they are one
+//! (`true`, `3`, `["a", "b"]`) and as a string otherwise.
+
+use crate::{
+    errors::ConfigError,
+    loader::include_not_supported,
+    provenance::{Provenance, ValueSource},
+    secret::decrypt_secrets,
+    traits::ConfigurationProvider,
+    types::AppConfig,
+    validation::Validator,
+};
+use serde::Deserialize;
+use toml::{Table, Value};
+
+/// A single parsed `field=value` override.
+#[derive(Debug, Clone, PartialEq)]
+struct Override {
+    arg: String,
+    field: String,
+    value: Value,
+}
+
+impl Override {
+    fn parse(arg: &str) -> Result<Self, ConfigError> {
+        let invalid = |reason: &str| ConfigError::InvalidOverride {
+            arg: arg.to_string(),
+            reason: reason.to_string(),
+        };
+        let (field, value) = arg
+            .split_once('=')
+            .ok_or_else(|| invalid("expected `field=value`"))?;
+        let field = field.trim();
+        if field.is_empty() || field.split('.').any(str::is_empty) {
+            return Err(invalid(
+                "the field must be a dotted path such as `log_level`",
+            ));
+        }
+        Ok(Self {
+            arg: arg.to_string(),
+            field: field.to_string(),
+            value: parse_value(value),
+        })
+    }
+
+    /// Returns `true` if this override sets `field`, either directly, as
+    /// part of a table, or through one of its keys.
+    fn sets(&self, field: &str) -> bool {
+        let within = |outer: &str, inner: &str| {
+            inner
+                .strip_prefix(outer)
+                .is_some_and(|rest| rest.starts_with('.'))
+        };
+        self.field == field || within(&self.field, field) || within(field, &self.field)
+    }
+
+    fn insert_into(&self, root: &mut Table) -> Result<(), ConfigError> {
+        let conflict = || ConfigError::InvalidOverride {
+            arg: self.arg.clone(),
+            reason: "conflicts with an earlier override".to_string(),
+        };
+        let (parents, key) = match self.field.rsplit_once('.') {
+            Some((parents, key)) => (Some(parents), key),
+            None => (None, self.field.as_str()),
+        };
+        let mut table = root;
+        for segment in parents.into_iter().flat_map(|parents| parents.split('.')) {
+            table = table
+                .entry(segment)
+                .or_insert_with(|| Value::Table(Table::new()))
+                .as_table_mut()
+                .ok_or_else(conflict)?;
+        }
+        table.insert(key.to_string(), self.value.clone());
+        Ok(())
+    }
+}
+
+/// Reads `value` as a TOML value, falling back to a plain string.
+fn parse_value(value: &str) -> Value {
+    toml::from_str::<Table>(&format!("value = {}", value))
+        .ok()
+        .filter(|table| table.len() == 1)
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+/// A configuration provider for `--set field=value` overrides.
+///
+/// Pass it to `LayeredConfigurationProvider::with_overrides`, which applies
+/// it after every other layer, so that a single run can change a setting
+/// without editing any file:
+///
+/// ```
+/// use config::{
+///     args::ArgsConfigurationProvider, layers::LayeredConfigurationProvider,
+///     loader::FileConfigurationProvider, traits::ConfigurationProvider, types::LogLevel,
+/// };
+///
+/// let overrides = ArgsConfigurationProvider::new(["log_level=debug"]).unwrap();
+/// let provider = LayeredConfigurationProvider::new()
+///     .with_provider(Box::new(FileConfigurationProvider::new("ciphr.toml")))
+///     .with_overrides(overrides);
+///
+/// let config = provider.load().unwrap().unwrap();
+/// assert_eq!(config.log_level, Some(LogLevel::Debug));
+/// ```
+pub struct ArgsConfigurationProvider {
+    overrides: Vec<Override>,
+}
+
+impl ArgsConfigurationProvider {
+    /// Parses the given `field=value` overrides. Later overrides of the
+    /// same field win.
+    ///
+    /// Fails with `ConfigError::InvalidOverride` if an argument has no `=`
+    /// or sets a value the field does not accept, and with
+    /// `ConfigError::InvalidInclude` if it sets `include`.
+    pub fn new<I, S>(args: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let overrides = args
+            .into_iter()
+            .map(|arg| Override::parse(arg.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Check each override on its own, so that a wrong value is reported
+        // against the argument that set it.
+        let mut combined = Table::new();
+        for item in &overrides {
+            if item.field == "include" {
+                return Err(include_not_supported(&item.arg, "--set overrides"));
+            }
+            item.insert_into(&mut combined)?;
+            let mut table = Table::new();
+            item.insert_into(&mut table)?;
+            AppConfig::deserialize(Value::Table(table)).map_err(|e| {
+                ConfigError::InvalidOverride {
+                    arg: item.arg.clone(),
+                    reason: e.message().to_string(),
+                }
+            })?;
+        }
+        Ok(Self { overrides })
+    }
+
+    /// Returns `true` if no overrides were given.
+    pub fn is_empty(&self) -> bool {
+        self.overrides.is_empty()
+    }
+}
+
+impl ConfigurationProvider for ArgsConfigurationProvider {
+    fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        if self.overrides.is_empty() {
+            return Ok(None);
+        }
+        let mut table = Table::new();
+        for item in &self.overrides {
+            item.insert_into(&mut table)?;
+        }
+        let mut config = AppConfig::deserialize(Value::Table(table))?;
+        decrypt_secrets(&mut config, None)?;
+        Ok(Some(config))
+    }
+
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        let Some(config) = self.load()? else {
+            return Ok(None);
+        };
+        let provenance = Provenance::from_config(&config, |field| {
+            let arg = self
+                .overrides
+                .iter()
+                .rev()
+                .find(|item| item.sets(field))
+                .map_or("", |item| item.arg.as_str());
+            ValueSource::named(format!("command-line argument --set {}", arg))
+        });
+        Ok(Some((config, provenance)))
+    }
+
+    fn name(&self) -> String {
+        "command-line arguments".to_string()
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        Validator::default().validate(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layers::LayeredConfigurationProvider, loader::FileConfigurationProvider, types::LogLevel,
+    };
+    use test_utils::mock_fs::create_temp_config_file;
+
+    #[test]
+    fn test_load_overrides() {
+        let provider = ArgsConfigurationProvider::new([
+            "log_level=debug",
+            "feature_flags.beta=true",
+            "feature_flags.new_ui.rollout=0.5",
+            "monitoring.endpoint=http://localhost:9090",
+            "log_level=warn",
+        ])
+        .unwrap();
+
+        let config = provider.load().unwrap().unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Warn));
+        assert!(config.feature_flags["beta"].is_enabled());
+        assert_eq!(config.feature_flags["new_ui"].rollout, Some(0.5));
+        assert_eq!(
+            config.sections["monitoring"]["endpoint"],
+            "http://localhost:9090"
+        );
+        assert_eq!(config.environment, None);
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        for (arg, reason) in [
+            ("log_level", "expected `field=value`"),
+            (".beta=true", "the field must be a dotted path"),
+            ("log_level=verbose", "unknown variant `verbose`"),
+        ] {
+            match ArgsConfigurationProvider::new([arg]) {
+                Err(ConfigError::InvalidOverride {
+                    arg: failed,
+                    reason: message,
+                }) => {
+                    assert_eq!(failed, arg);
+                    assert!(message.contains(reason), "{}", message);
+                }
+                other => panic!("expected {} to be rejected, got {:?}", arg, other.err()),
+            }
+        }
+
+        let result = ArgsConfigurationProvider::new(["monitoring=1", "monitoring.port=2"]);
+        assert!(matches!(result, Err(ConfigError::InvalidOverride { .. })));
+
+        let result = ArgsConfigurationProvider::new(["include=[\"local.toml\"]"]);
+        assert!(matches!(result, Err(ConfigError::InvalidInclude { .. })));
+    }
+
+    #[test]
+    fn test_flag_table_override_keeps_disabled_flag() {
+        let file =
+            create_temp_config_file("[feature_flags.new_ui]\nenabled = false\nrollout = 0.5\n");
+        let provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(file.path())))
+            .with_provider(Box::new(
+                ArgsConfigurationProvider::new(["feature_flags.new_ui.description=x"]).unwrap(),
+            ));
+
+        let flag = &provider.load().unwrap().unwrap().feature_flags["new_ui"];
+        assert!(!flag.is_enabled());
+        assert_eq!(flag.rollout, Some(0.5));
+        assert_eq!(flag.description.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn test_highest_precedence_with_provenance() {
+        let file = create_temp_config_file("log_level = \"info\"\nlog_format = \"json\"\n");
+        let provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(file.path())))
+            .with_provider(Box::new(
+                ArgsConfigurationProvider::new(["log_level=debug"]).unwrap(),
+            ));
+
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(
+            provenance.get("log_level").unwrap().to_string(),
+            format!(
+                "set by command-line argument --set log_level=debug, overriding {}:1",
+                file.path().display()
+            )
+        );
+        assert_eq!(
+            provenance.get("log_format").unwrap().source.path.as_deref(),
+            Some(file.path())
+        );
+    }
+}
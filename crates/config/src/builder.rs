@@ -1,16 +1,24 @@
+use crate::errors::ConfigError;
+use crate::flag::FlagDefinition;
+use crate::secret::Secret;
 use crate::types::{AppConfig, LogFormat, LogLevel};
+use crate::validation::{RequiredFields, ValidationErrors, ValidationRule, Validator};
 use std::collections::HashMap;
 
 /// A builder for creating `AppConfig` instances.
 ///
 /// This builder provides a fluent API for constructing an `AppConfig`
-/// piece by piece, with validation occurring at build time.
+/// piece by piece. `build` fills in defaults and never fails; `try_build`
+/// also runs the same validation rules as the file providers.
 #[derive(Default)]
 pub struct AppConfigBuilder {
     environment: Option<String>,
     log_level: Option<LogLevel>,
     log_format: Option<LogFormat>,
-    feature_flags: HashMap<String, bool>,
+    feature_flags: HashMap<String, FlagDefinition>,
+    secrets: HashMap<String, Secret<String>>,
+    validator: Validator,
+    required: RequiredFields,
 }
 
 impl AppConfigBuilder {
@@ -37,9 +45,41 @@ impl AppConfigBuilder {
         self
     }
 
-    /// Adds a feature flag to the configuration.
-    pub fn feature_flag(mut self, key: impl Into<String>, value: bool) -> Self {
-        self.feature_flags.insert(key.into(), value);
+    /// Adds a feature flag to the configuration, either as a plain
+    /// `bool` or as a full `FlagDefinition`.
+    pub fn feature_flag(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<FlagDefinition>,
+    ) -> Self {
+        self.feature_flags.insert(key.into(), value.into());
+        self
+    }
+
+    /// Adds a secret value to the configuration.
+    pub fn secret(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.secrets.insert(key.into(), Secret::new(value.into()));
+        self
+    }
+
+    /// Replaces the rules used by `try_build`.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Makes `try_build` fail unless `field` is set explicitly, instead of
+    /// being left to its default.
+    pub fn require(mut self, field: impl Into<String>) -> Self {
+        self.required = self.required.require(field);
+        self
+    }
+
+    /// Like `require`, but only when `environment` is `environment`.
+    pub fn require_in(mut self, environment: impl Into<String>, field: impl Into<String>) -> Self {
+        self.required = self.required.require_in(environment, field);
         self
     }
 
@@ -47,19 +87,53 @@ impl AppConfigBuilder {
     ///
     /// This method will use default values for any fields that have not
     /// been explicitly set.
-    pub fn build(self) -> AppConfig {
+    pub fn build(mut self) -> AppConfig {
+        with_defaults(self.explicit())
+    }
+
+    /// Builds the `AppConfig` and validates it.
+    ///
+    /// Required fields are checked before defaults are filled in, and the
+    /// validation rules after. Every violation is returned at once as
+    /// `ConfigError::Validation`.
+    pub fn try_build(mut self) -> Result<AppConfig, ConfigError> {
+        let explicit = self.explicit();
+        let mut failures = self.required.check(&explicit);
+        let config = with_defaults(explicit);
+        failures.extend(self.validator.check(&config));
+        if failures.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError::Validation(ValidationErrors(failures)))
+        }
+    }
+
+    /// Takes the fields that were set, without defaults.
+    fn explicit(&mut self) -> AppConfig {
         AppConfig {
-            environment: Some(self.environment.unwrap_or_else(|| "development".to_string())),
-            log_level: Some(self.log_level.unwrap_or(LogLevel::Info)),
-            log_format: Some(self.log_format.unwrap_or(LogFormat::Text)),
-            feature_flags: self.feature_flags,
+            environment: self.environment.take(),
+            log_level: self.log_level.take(),
+            log_format: self.log_format.take(),
+            feature_flags: std::mem::take(&mut self.feature_flags),
+            secrets: std::mem::take(&mut self.secrets),
+            ..Default::default()
         }
     }
 }
 
+fn with_defaults(mut config: AppConfig) -> AppConfig {
+    config
+        .environment
+        .get_or_insert_with(|| "development".to_string());
+    config.log_level.get_or_insert(LogLevel::Info);
+    config.log_format.get_or_insert(LogFormat::Text);
+    config
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::validation::{FlagRolloutRange, NoTraceInProduction};
 
     #[test]
     fn test_builder_defaults() {
@@ -82,6 +156,64 @@ mod tests {
         assert_eq!(config.environment, Some("production".to_string()));
         assert_eq!(config.log_level, Some(LogLevel::Debug));
         assert_eq!(config.log_format, Some(LogFormat::Json));
-        assert_eq!(config.feature_flags.get("new_feature"), Some(&true));
+        assert!(config.feature_flags["new_feature"].is_enabled());
+    }
+
+    #[test]
+    fn test_try_build_validates() {
+        let config = AppConfigBuilder::new()
+            .environment("production")
+            .try_build()
+            .unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Info));
+
+        let result = AppConfigBuilder::new()
+            .with_validator(
+                Validator::new()
+                    .with_rule(NoTraceInProduction)
+                    .with_rule(FlagRolloutRange),
+            )
+            .environment("production")
+            .log_level(LogLevel::Trace)
+            .feature_flag(
+                "new_ui",
+                FlagDefinition {
+                    enabled: Some(true),
+                    rollout: Some(2.0),
+                    ..Default::default()
+                },
+            )
+            .try_build();
+        match result {
+            Err(ConfigError::Validation(errors)) => {
+                let rules: Vec<&str> = errors.failures().iter().map(|f| f.rule.as_str()).collect();
+                assert_eq!(rules, vec!["no-trace-in-production", "flag-rollout"]);
+            }
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_try_build_required_fields() {
+        let builder = || {
+            AppConfigBuilder::new()
+                .require("environment")
+                .require_in("production", "log_format")
+        };
+
+        match builder().log_level(LogLevel::Debug).try_build() {
+            Err(ConfigError::Validation(errors)) => {
+                assert_eq!(errors.failures().len(), 1);
+                assert_eq!(errors.failures()[0].field, "environment");
+            }
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+        assert!(builder().environment("production").try_build().is_err());
+        assert!(builder().environment("staging").try_build().is_ok());
+        assert!(builder()
+            .environment("production")
+            .log_format(LogFormat::Json)
+            .try_build()
+            .is_ok());
     }
 } 
\ No newline at end of file
@@ -0,0 +1,446 @@
+//! Readable diagnostics for configuration errors.
+//!
+//! `diagnose` turns a `ConfigError` into one `Diagnostic` per problem. Each
+//! names the file and position of the problem where it is known, and
+//! renders the offending line with a caret under the bad value and a hint
+//! on how to fix it:
+//!
+//! ```text
+//! error: unknown variant `verbose`
+//!  --> ciphr.toml:2:13
+//!   |
+//! 2 | log_level = "verbose"
+//!   |             ^^^^^^^^^
+//!   = help: expected one of `trace`, `debug`, `info`, `warn`, `error`
+//! ```
+
+use crate::{
+    errors::ConfigError,
+    format::ConfigFormat,
+    loader::{key_span, value_span},
+    provenance::{Provenance, ValueSource},
+    strict::UnknownKey,
+    validation::ValidationFailure,
+};
+use std::{fmt, fs, ops::Range, path::Path};
+
+/// The highlighted part of one line of a configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// The 1-based line number.
+    pub line: usize,
+    /// The 1-based column, in characters, where the highlight starts.
+    pub column: usize,
+    /// The number of characters highlighted, at least one.
+    pub length: usize,
+    /// The text of the line, without its line ending.
+    pub text: String,
+}
+
+impl Span {
+    /// Highlights the byte range `range` of `contents`, up to the end of the
+    /// line it starts on.
+    pub fn from_range(contents: &str, range: Range<usize>) -> Option<Self> {
+        let start = range.start;
+        if !contents.is_char_boundary(start) {
+            return None;
+        }
+        let line_start = contents[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = contents[start..]
+            .find('\n')
+            .map_or(contents.len(), |i| start + i);
+        let end = range.end.clamp(start, line_end);
+        Some(Self {
+            line: contents[..start].matches('\n').count() + 1,
+            column: contents[line_start..start].chars().count() + 1,
+            length: contents
+                .get(start..end)
+                .map_or(0, |s| s.chars().count())
+                .max(1),
+            text: contents[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+        })
+    }
+
+    /// Highlights a single character at a 1-based `line` and `column`, for
+    /// errors that only report a position.
+    pub fn at(contents: &str, line: usize, column: usize) -> Option<Self> {
+        let text = contents.lines().nth(line.checked_sub(1)?)?;
+        Some(Self {
+            line,
+            column: column.max(1),
+            length: 1,
+            text: text.to_string(),
+        })
+    }
+
+    /// Highlights a whole line, apart from its indentation.
+    pub fn line(contents: &str, line: usize) -> Option<Self> {
+        let text = contents.lines().nth(line.checked_sub(1)?)?;
+        let indent = text.chars().take_while(|c| c.is_whitespace()).count();
+        Some(Self {
+            line,
+            column: indent + 1,
+            length: text.trim().chars().count().max(1),
+            text: text.to_string(),
+        })
+    }
+}
+
+/// A single problem with a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The kind of problem, e.g. the name of the validation rule that failed.
+    pub code: Option<String>,
+    /// What is wrong.
+    pub message: String,
+    /// Where the problem is: a file path, or another source such as an
+    /// environment variable.
+    pub origin: Option<String>,
+    /// The offending part of the file named by `origin`.
+    pub span: Option<Span>,
+    /// A hint on how to fix the problem.
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    /// Creates a diagnostic with only a message.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            code: None,
+            message: message.into(),
+            origin: None,
+            span: None,
+            help: None,
+        }
+    }
+
+    /// Sets the kind of problem.
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Sets where the problem is.
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.origin = Some(origin.into());
+        self
+    }
+
+    /// Points at the offending part of the file.
+    pub fn with_span(mut self, span: Option<Span>) -> Self {
+        self.span = span;
+        self
+    }
+
+    /// Sets the hint on how to fix the problem.
+    pub fn with_help(mut self, help: Option<String>) -> Self {
+        self.help = help;
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.code {
+            Some(code) => writeln!(f, "error[{}]: {}", code, self.message)?,
+            None => writeln!(f, "error: {}", self.message)?,
+        }
+        let gutter = " ".repeat(
+            self.span
+                .as_ref()
+                .map_or(1, |span| span.line.to_string().len()),
+        );
+        match (&self.origin, &self.span) {
+            (Some(origin), Some(span)) => {
+                writeln!(f, "{}--> {}:{}:{}", gutter, origin, span.line, span.column)?;
+                // Keep tabs so that the caret lines up with the text.
+                let padding: String = span
+                    .text
+                    .chars()
+                    .chain(std::iter::repeat(' '))
+                    .take(span.column - 1)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' })
+                    .collect();
+                writeln!(f, "{} |", gutter)?;
+                writeln!(f, "{} | {}", span.line, span.text)?;
+                writeln!(f, "{} | {}{}", gutter, padding, "^".repeat(span.length))?;
+            }
+            (Some(origin), None) => writeln!(f, "{}--> {}", gutter, origin)?,
+            (None, _) => {}
+        }
+        if let Some(help) = &self.help {
+            writeln!(f, "{} = help: {}", gutter, help)?;
+        }
+        Ok(())
+    }
+}
+
+/// Describes each problem in `error`.
+///
+/// Files named by the error are read again to show the offending lines.
+/// `provenance`, as returned by `load_with_provenance`, locates the fields
+/// that failed validation; without it, those have no location.
+pub fn diagnose(error: &ConfigError, provenance: Option<&Provenance>) -> Vec<Diagnostic> {
+    match error {
+        ConfigError::File { path, source } => diagnose_file(source, path, provenance),
+        ConfigError::UnknownKeys(keys) => keys.keys().iter().map(unknown_key).collect(),
+        ConfigError::Validation(errors) => errors
+            .failures()
+            .iter()
+            .map(|failure| validation_failure(failure, provenance))
+            .collect(),
+        ConfigError::InvalidEnvVar {
+            name,
+            value,
+            expected,
+        } => vec![Diagnostic::new(format!("invalid value {:?}", value))
+            .with_origin(format!("environment variable {}", name))
+            .with_help(Some(format!("expected {}", expected)))],
+        ConfigError::IncludeCycle(_) => vec![Diagnostic::new(error.to_string()).with_help(Some(
+            "remove one of the `include` entries that form the cycle".to_string(),
+        ))],
+        _ => vec![Diagnostic::new(error.to_string())],
+    }
+}
+
+/// Describes an error found in the file at `path`, pointing at the position
+/// reported by the parser.
+fn diagnose_file(
+    error: &ConfigError,
+    path: &Path,
+    provenance: Option<&Provenance>,
+) -> Vec<Diagnostic> {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let diagnostic = match error {
+        ConfigError::Toml(e) => parse_error(e.message()).with_span(
+            e.span()
+                .and_then(|range| Span::from_range(&contents, range)),
+        ),
+        ConfigError::Json(e) => {
+            parse_error(&e.to_string()).with_span(Span::at(&contents, e.line(), e.column()))
+        }
+        ConfigError::Json5(json5::Error::Message { msg, location }) => parse_error(msg).with_span(
+            location
+                .as_ref()
+                .and_then(|location| Span::at(&contents, location.line, location.column)),
+        ),
+        ConfigError::Yaml(e) => parse_error(&e.to_string()).with_span(
+            e.location()
+                .and_then(|location| Span::at(&contents, location.line(), location.column())),
+        ),
+        _ => {
+            return diagnose(error, provenance)
+                .into_iter()
+                .map(|diagnostic| match diagnostic.origin {
+                    Some(_) => diagnostic,
+                    None => diagnostic.with_origin(path.display().to_string()),
+                })
+                .collect()
+        }
+    };
+    vec![diagnostic.with_origin(path.display().to_string())]
+}
+
+/// Splits a parser message such as ``unknown variant `verbose`, expected
+/// one of …`` into the problem and a hint.
+fn parse_error(message: &str) -> Diagnostic {
+    // JSON and YAML errors end with the position, which the span shows.
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message, |(message, _)| message)
+        .trim();
+    match message.split_once(", expected ") {
+        Some((problem, expected)) => {
+            Diagnostic::new(problem).with_help(Some(format!("expected {}", expected)))
+        }
+        None => Diagnostic::new(message),
+    }
+}
+
+fn unknown_key(key: &UnknownKey) -> Diagnostic {
+    let contents = fs::read_to_string(&key.path).unwrap_or_default();
+    let span = key_span(&contents, &key.key)
+        .and_then(|range| Span::from_range(&contents, range))
+        .or_else(|| Span::line(&contents, key.line?));
+    Diagnostic::new(format!("unknown key `{}`", key.key))
+        .with_code("unknown-key")
+        .with_origin(key.path.display().to_string())
+        .with_span(span)
+        .with_help(
+            key.suggestion
+                .as_ref()
+                .map(|suggestion| format!("did you mean `{}`?", suggestion)),
+        )
+}
+
+fn validation_failure(failure: &ValidationFailure, provenance: Option<&Provenance>) -> Diagnostic {
+    let diagnostic = Diagnostic::new(format!("{}: {}", failure.field, failure.message))
+        .with_code(&failure.rule)
+        .with_help(failure.help.clone());
+    locate(diagnostic, &failure.field, provenance)
+}
+
+/// Points `diagnostic` at the value of `field` in the layer that set it.
+///
+/// Values can only be found in TOML files; in other formats the diagnostic
+/// names the file without a position.
+fn locate(diagnostic: Diagnostic, field: &str, provenance: Option<&Provenance>) -> Diagnostic {
+    let Some(source) = provenance.and_then(|provenance| source_of(provenance, field)) else {
+        return diagnostic;
+    };
+    let Some(path) = &source.path else {
+        return diagnostic.with_origin(source.provider.clone());
+    };
+    if ConfigFormat::from_path(path).is_some_and(|format| format != ConfigFormat::Toml) {
+        return diagnostic.with_origin(path.display().to_string());
+    }
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let span = value_span(&contents, field)
+        .and_then(|range| Span::from_range(&contents, range))
+        .or_else(|| Span::line(&contents, source.line?));
+    diagnostic
+        .with_origin(path.display().to_string())
+        .with_span(span)
+}
+
+/// Returns the source of `field`, or of the closest enclosing field that
+/// provenance tracks, e.g. `feature_flags.new_ui` for
+/// `feature_flags.new_ui.rollout`.
+fn source_of<'a>(provenance: &'a Provenance, mut field: &str) -> Option<&'a ValueSource> {
+    loop {
+        if let Some(found) = provenance.get(field) {
+            return Some(&found.source);
+        }
+        field = field.rsplit_once('.')?.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        loader::FileConfigurationProvider,
+        traits::ConfigurationProvider,
+        validation::{AllowedEnvironments, FlagRolloutRange, Validator},
+    };
+    use test_utils::mock_fs::{create_temp_config_file, create_temp_file_with_extension};
+
+    #[test]
+    fn test_parse_error_snippet() {
+        let file =
+            create_temp_config_file("environment = \"production\"\nlog_level = \"verbose\"\n");
+        let error = FileConfigurationProvider::new(file.path())
+            .load()
+            .unwrap_err();
+
+        let diagnostics = diagnose(&error, None);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            format!(
+                "error: unknown variant `verbose`\n \
+                 --> {}:2:13\n  \
+                 |\n\
+                 2 | log_level = \"verbose\"\n  \
+                 |             ^^^^^^^^^\n  \
+                 = help: expected one of `trace`, `debug`, `info`, `warn`, `error`\n",
+                file.path().display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_parse_error_position() {
+        let file = create_temp_file_with_extension(".json", "{\n  \"log_level\": \"debug\",\n}\n");
+        let error = FileConfigurationProvider::new(file.path())
+            .load()
+            .unwrap_err();
+
+        let diagnostic = &diagnose(&error, None)[0];
+        assert_eq!(diagnostic.message, "trailing comma");
+        assert_eq!(
+            diagnostic.origin.as_deref(),
+            Some(file.path().to_str().unwrap())
+        );
+        assert_eq!(diagnostic.span.as_ref().unwrap().line, 3);
+    }
+
+    #[test]
+    fn test_validation_failure_points_at_value() {
+        let file = create_temp_config_file(
+            "environment = \"prodution\"\n\n[feature_flags.new_ui]\nrollout = 1.5\n",
+        );
+        let provider = FileConfigurationProvider::new(file.path());
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+        let error = Validator::new()
+            .with_rule(AllowedEnvironments::default())
+            .with_rule(FlagRolloutRange)
+            .validate(&config)
+            .unwrap_err();
+
+        let diagnostics = diagnose(&error, Some(&provenance));
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("allowed-environment"));
+        assert_eq!(
+            diagnostics[0].help.as_deref(),
+            Some("did you mean `production`?")
+        );
+        let span = diagnostics[0].span.as_ref().unwrap();
+        assert_eq!((span.line, span.column, span.length), (1, 15, 11));
+
+        assert_eq!(diagnostics[1].code.as_deref(), Some("flag-rollout"));
+        let span = diagnostics[1].span.as_ref().unwrap();
+        assert_eq!((span.line, span.column, span.length), (4, 11, 3));
+        assert!(diagnostics[1]
+            .to_string()
+            .ends_with("4 | rollout = 1.5\n  |           ^^^\n  = help: use a fraction of users, e.g. `0.25` for 25%\n"));
+    }
+
+    #[test]
+    fn test_validation_failure_in_other_formats_names_the_file() {
+        let file = create_temp_file_with_extension(
+            ".json",
+            r#"{ "feature_flags": { "new_ui": { "rollout": 1.5 } } }"#,
+        );
+        let provider = FileConfigurationProvider::new(file.path());
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+        let error = Validator::default().validate(&config).unwrap_err();
+
+        let diagnostics = diagnose(&error, Some(&provenance));
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].origin.as_deref(),
+            Some(file.path().to_str().unwrap())
+        );
+        assert_eq!(diagnostics[0].span, None);
+    }
+
+    #[test]
+    fn test_unknown_keys_and_other_sources() {
+        let file = create_temp_config_file("log_levl = \"debug\"\n");
+        let error = FileConfigurationProvider::new(file.path())
+            .strict()
+            .load()
+            .unwrap_err();
+
+        let diagnostic = &diagnose(&error, None)[0];
+        assert_eq!(diagnostic.message, "unknown key `log_levl`");
+        assert_eq!(
+            diagnostic.help.as_deref(),
+            Some("did you mean `log_level`?")
+        );
+        assert_eq!(diagnostic.span.as_ref().unwrap().length, 8);
+
+        let error = ConfigError::InvalidEnvVar {
+            name: "CIPHR_LOG_LEVEL".to_string(),
+            value: "loud".to_string(),
+            expected: "a log level".to_string(),
+        };
+        assert_eq!(
+            diagnose(&error, None)[0].to_string(),
+            "error: invalid value \"loud\"\n --> environment variable CIPHR_LOG_LEVEL\n  = help: expected a log level\n"
+        );
+    }
+}
@@ -0,0 +1,248 @@
+use crate::{section::leaf_fields, types::AppConfig};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// A single difference between two `AppConfig` values.
+///
+/// Fields are identified by their dotted path, e.g. `log_level` or
+/// `feature_flags.new_ui`. Values are rendered as strings so that changes
+/// to differently typed fields can be reported uniformly.
+///
+/// Serializes with a `kind` tag, e.g.
+/// `{"kind":"changed","field":"log_level","old":"info","new":"debug"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ConfigChange {
+    /// The field is set in the new config but not in the old one.
+    Added { field: String, value: String },
+    /// The field was set in the old config but is not in the new one.
+    Removed { field: String, value: String },
+    /// The field is set in both configs with different values.
+    Changed {
+        field: String,
+        old: String,
+        new: String,
+    },
+}
+
+impl ConfigChange {
+    /// Returns the dotted path of the field this change refers to.
+    pub fn field(&self) -> &str {
+        match self {
+            ConfigChange::Added { field, .. }
+            | ConfigChange::Removed { field, .. }
+            | ConfigChange::Changed { field, .. } => field,
+        }
+    }
+}
+
+impl fmt::Display for ConfigChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigChange::Added { field, value } => write!(f, "+ {} = {}", field, value),
+            ConfigChange::Removed { field, value } => write!(f, "- {} = {}", field, value),
+            ConfigChange::Changed { field, old, new } => {
+                write!(f, "~ {}: {} -> {}", field, old, new)
+            }
+        }
+    }
+}
+
+fn compare(
+    changes: &mut Vec<ConfigChange>,
+    field: String,
+    old: Option<String>,
+    new: Option<String>,
+) {
+    match (old, new) {
+        (None, Some(value)) => changes.push(ConfigChange::Added { field, value }),
+        (Some(value), None) => changes.push(ConfigChange::Removed { field, value }),
+        (Some(old), Some(new)) if old != new => {
+            changes.push(ConfigChange::Changed { field, old, new })
+        }
+        _ => {}
+    }
+}
+
+/// Returns the leaves of every section keyed by dotted path.
+fn section_fields(config: &AppConfig) -> BTreeMap<String, String> {
+    let mut fields = Vec::new();
+    for (name, value) in &config.sections {
+        leaf_fields(name.clone(), value, &mut fields);
+    }
+    fields.into_iter().collect()
+}
+
+/// Compares two configurations and returns every field that differs.
+///
+/// Changes are returned in a stable order: top-level fields first, followed
+/// by feature flags, section values and secrets sorted by name. Secrets are compared by value
+/// but always reported as `[REDACTED]`.
+pub fn diff_configs(old: &AppConfig, new: &AppConfig) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+
+    compare(
+        &mut changes,
+        "environment".to_string(),
+        old.environment.clone(),
+        new.environment.clone(),
+    );
+    compare(
+        &mut changes,
+        "log_level".to_string(),
+        old.log_level.as_ref().map(ToString::to_string),
+        new.log_level.as_ref().map(ToString::to_string),
+    );
+    compare(
+        &mut changes,
+        "log_format".to_string(),
+        old.log_format.as_ref().map(ToString::to_string),
+        new.log_format.as_ref().map(ToString::to_string),
+    );
+
+    let flag_names: BTreeSet<&String> = old
+        .feature_flags
+        .keys()
+        .chain(new.feature_flags.keys())
+        .collect();
+    for name in flag_names {
+        compare(
+            &mut changes,
+            format!("feature_flags.{}", name),
+            old.feature_flags.get(name).map(ToString::to_string),
+            new.feature_flags.get(name).map(ToString::to_string),
+        );
+    }
+
+    let (old_sections, new_sections) = (section_fields(old), section_fields(new));
+    let section_paths: BTreeSet<&String> = old_sections.keys().chain(new_sections.keys()).collect();
+    for path in section_paths {
+        compare(
+            &mut changes,
+            path.clone(),
+            old_sections.get(path).cloned(),
+            new_sections.get(path).cloned(),
+        );
+    }
+
+    let secret_names: BTreeSet<&String> = old.secrets.keys().chain(new.secrets.keys()).collect();
+    for name in secret_names {
+        let field = format!("secrets.{}", name);
+        match (old.secrets.get(name), new.secrets.get(name)) {
+            (Some(old), Some(new)) if old != new => changes.push(ConfigChange::Changed {
+                field,
+                old: old.to_string(),
+                new: new.to_string(),
+            }),
+            (old, new) => compare(
+                &mut changes,
+                field,
+                old.map(ToString::to_string),
+                new.map(ToString::to_string),
+            ),
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::AppConfigBuilder;
+    use crate::types::LogLevel;
+
+    #[test]
+    fn test_identical_configs_have_no_changes() {
+        let config = AppConfigBuilder::new().feature_flag("new_ui", true).build();
+        assert!(diff_configs(&config, &config.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_detects_added_removed_and_changed() {
+        let old = AppConfigBuilder::new()
+            .log_level(LogLevel::Info)
+            .feature_flag("old_reports", true)
+            .feature_flag("new_ui", false)
+            .build();
+        let mut new = AppConfigBuilder::new()
+            .log_level(LogLevel::Debug)
+            .feature_flag("new_ui", true)
+            .feature_flag("beta", true)
+            .build();
+        new.environment = None;
+
+        let changes = diff_configs(&old, &new);
+
+        assert_eq!(
+            changes,
+            vec![
+                ConfigChange::Removed {
+                    field: "environment".to_string(),
+                    value: "development".to_string(),
+                },
+                ConfigChange::Changed {
+                    field: "log_level".to_string(),
+                    old: "info".to_string(),
+                    new: "debug".to_string(),
+                },
+                ConfigChange::Added {
+                    field: "feature_flags.beta".to_string(),
+                    value: "true".to_string(),
+                },
+                ConfigChange::Changed {
+                    field: "feature_flags.new_ui".to_string(),
+                    old: "false".to_string(),
+                    new: "true".to_string(),
+                },
+                ConfigChange::Removed {
+                    field: "feature_flags.old_reports".to_string(),
+                    value: "true".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_serialize() {
+        let change = ConfigChange::Changed {
+            field: "log_level".to_string(),
+            old: "info".to_string(),
+            new: "debug".to_string(),
+        };
+        assert_eq!(
+            serde_json::to_value(&change).unwrap(),
+            serde_json::json!({
+                "kind": "changed",
+                "field": "log_level",
+                "old": "info",
+                "new": "debug",
+            })
+        );
+    }
+
+    #[test]
+    fn test_secret_changes_are_redacted() {
+        let old = AppConfigBuilder::new().secret("db_password", "old").build();
+        let new = AppConfigBuilder::new()
+            .secret("db_password", "new")
+            .secret("api_key", "key")
+            .build();
+
+        assert_eq!(
+            diff_configs(&old, &new),
+            vec![
+                ConfigChange::Added {
+                    field: "secrets.api_key".to_string(),
+                    value: "[REDACTED]".to_string(),
+                },
+                ConfigChange::Changed {
+                    field: "secrets.db_password".to_string(),
+                    old: "[REDACTED]".to_string(),
+                    new: "[REDACTED]".to_string(),
+                },
+            ]
+        );
+    }
+}
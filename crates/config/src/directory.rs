@@ -0,0 +1,203 @@
+//! Loads every configuration file in a directory, such as `conf.d`.
+
+use crate::{
+    errors::ConfigError, format::ConfigFormat, layers::merge_config,
+    loader::FileConfigurationProvider, migration::MigrationRegistry, provenance::Provenance,
+    secret::SecretKey, traits::ConfigurationProvider, types::AppConfig, validation::Validator,
+};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A configuration provider that layers the files in a directory.
+///
+/// Files are loaded in lexical order of their names, each on top of the
+/// ones before, so `10-base.toml` is overridden by `20-local.toml`. Only
+/// files with a recognised extension (see `ConfigFormat::from_path`) are
+/// read; hidden files and subdirectories are skipped. Each file may use
+/// `include` like any other configuration file.
+///
+/// A missing directory is treated like a missing file and loads nothing.
+pub struct DirectoryConfigurationProvider {
+    dir: PathBuf,
+    validator: Validator,
+    secret_key: Option<SecretKey>,
+    migrations: MigrationRegistry,
+}
+
+impl DirectoryConfigurationProvider {
+    /// Creates a provider for the files in `dir`.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            validator: Validator::default(),
+            secret_key: None,
+            migrations: MigrationRegistry::default(),
+        }
+    }
+
+    /// Replaces the rules used by `validate`.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Sets the key used to decrypt secrets, instead of reading it from the
+    /// environment.
+    pub fn with_secret_key(mut self, key: SecretKey) -> Self {
+        self.secret_key = Some(key);
+        self
+    }
+
+    /// Replaces the migrations applied to files with an older
+    /// `config_version`.
+    ///
+    /// Defaults to `MigrationRegistry::default()`.
+    pub fn with_migrations(mut self, migrations: MigrationRegistry) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    /// Returns the configuration files in the directory in load order, or
+    /// `Ok(None)` if it does not exist.
+    pub fn files(&self) -> Result<Option<Vec<PathBuf>>, ConfigError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            if !hidden && path.is_file() && ConfigFormat::from_path(&path).is_some() {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(Some(files))
+    }
+
+    fn provider(&self, path: &Path) -> FileConfigurationProvider {
+        let provider =
+            FileConfigurationProvider::new(path).with_migrations(self.migrations.clone());
+        match &self.secret_key {
+            Some(key) => provider.with_secret_key(key.clone()),
+            None => provider,
+        }
+    }
+}
+
+impl ConfigurationProvider for DirectoryConfigurationProvider {
+    fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        Ok(self.load_with_provenance()?.map(|(config, _)| config))
+    }
+
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        let Some(files) = self.files()? else {
+            return Ok(None);
+        };
+        let mut merged = None;
+        for path in files {
+            if let Some((config, provenance)) = self.provider(&path).load_with_provenance()? {
+                let (merged_config, merged_provenance) =
+                    merged.get_or_insert_with(|| (AppConfig::default(), Provenance::new()));
+                merge_config(merged_config, config);
+                merged_provenance.merge(provenance);
+            }
+        }
+        Ok(merged)
+    }
+
+    fn name(&self) -> String {
+        "directory".to_string()
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        self.validator.validate(config)
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.dir.clone()];
+        for path in self.files().ok().flatten().unwrap_or_default() {
+            paths.extend(self.provider(&path).watch_paths());
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LogLevel;
+
+    #[test]
+    fn test_loads_files_in_lexical_order() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("20-local.toml"), "log_level = \"debug\"\n").unwrap();
+        fs::write(
+            dir.path().join("10-base.json"),
+            r#"{ "environment": "staging", "log_level": "info" }"#,
+        )
+        .unwrap();
+        fs::write(dir.path().join(".20-local.toml.swp"), "not toml").unwrap();
+        fs::write(dir.path().join("README.md"), "# Settings").unwrap();
+
+        let provider = DirectoryConfigurationProvider::new(dir.path());
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+        assert_eq!(config.environment, Some("staging".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(
+            provenance.get("log_level").unwrap().source.path,
+            Some(dir.path().join("20-local.toml"))
+        );
+    }
+
+    #[test]
+    fn test_migrates_every_file() {
+        use crate::migration::Migration;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("10-base.toml"), "log = \"info\"\n").unwrap();
+        fs::write(dir.path().join("20-local.toml"), "log = \"debug\"\n").unwrap();
+
+        let config = DirectoryConfigurationProvider::new(dir.path())
+            .with_migrations(MigrationRegistry::new().with_migration(
+                Migration::new(1, "rename `log` to `log_level`").rename("log", "log_level"),
+            ))
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_errors_name_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("10-base.toml"), "log_level = \"info\"\n").unwrap();
+        fs::write(dir.path().join("20-broken.toml"), "log_level = ").unwrap();
+
+        match DirectoryConfigurationProvider::new(dir.path()).load() {
+            Err(ConfigError::File { path, source }) => {
+                assert_eq!(path, dir.path().join("20-broken.toml"));
+                assert!(matches!(*source, ConfigError::Toml(_)));
+            }
+            other => panic!("expected a file error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_missing_or_empty_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = DirectoryConfigurationProvider::new(dir.path());
+        assert!(provider.load().unwrap().is_none());
+
+        let missing = DirectoryConfigurationProvider::new(dir.path().join("conf.d"));
+        assert!(missing.load().unwrap().is_none());
+    }
+}
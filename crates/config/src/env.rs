@@ -0,0 +1,364 @@
+//! Loads configuration overrides from `CIPHR_*` environment variables.
+//!
+//! Variables are matched against the provider's prefix (`CIPHR` by default)
+//! followed by an underscore. The remainder of the name is mapped onto
+//! `AppConfig` using the following rules:
+//!
+//! | Variable                      | Field                  | Accepted values                                  |
+//! |-------------------------------|------------------------|--------------------------------------------------|
+//! | `CIPHR_ENVIRONMENT`           | `environment`          | any string                                       |
+//! | `CIPHR_LOG_LEVEL`             | `log_level`            | `trace`, `debug`, `info`, `warn`, `error`        |
+//! | `CIPHR_LOG_FORMAT`            | `log_format`           | `text`, `json`                                   |
+//! | `CIPHR_FEATURE_FLAGS__<NAME>` | `feature_flags.<name>` | `true`/`false`, `1`/`0`, `yes`/`no`, `on`/`off`  |
+//!
+//! - Names are matched case-insensitively, so `CIPHR_log_level` works too.
+//! - Enum and boolean values are case-insensitive and surrounding whitespace
+//!   is ignored.
+//! - A double underscore (`__`) separates a table from the key inside it.
+//!   Keys are lowercased, so `CIPHR_FEATURE_FLAGS__NEW_UI` sets the
+//!   `new_ui` flag.
+//! - Variables with the prefix that do not map to a known field are ignored,
+//!   leaving the prefix free for other tools. `CIPHR_INCLUDE` is the
+//!   exception: `include` is only supported in files, so it is rejected
+//!   with [`ConfigError::InvalidInclude`] rather than silently ignored.
+//! - A value that cannot be parsed is reported as
+//!   [`ConfigError::InvalidEnvVar`] rather than silently skipped.
+
+use crate::{
+    errors::ConfigError,
+    loader::include_not_supported,
+    provenance::{Provenance, ValueSource},
+    traits::ConfigurationProvider,
+    types::{AppConfig, LogFormat, LogLevel},
+    validation::Validator,
+};
+use std::collections::HashMap;
+
+/// The prefix used when none is given explicitly.
+pub const DEFAULT_ENV_PREFIX: &str = "CIPHR";
+
+/// A configuration provider that reads settings from environment variables.
+///
+/// It is intended to be stacked on top of file-based providers in a
+/// `LayeredConfigurationProvider`, so that deployments can override
+/// individual settings without shipping a configuration file.
+pub struct EnvConfigurationProvider {
+    prefix: String,
+    vars: Option<HashMap<String, String>>,
+}
+
+impl EnvConfigurationProvider {
+    /// Creates a provider that reads `CIPHR_*` variables from the process
+    /// environment.
+    pub fn new() -> Self {
+        Self::with_prefix(DEFAULT_ENV_PREFIX)
+    }
+
+    /// Creates a provider that reads variables starting with `{prefix}_`.
+    pub fn with_prefix(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            vars: None,
+        }
+    }
+
+    /// Reads variables from the given set instead of the process environment.
+    ///
+    /// This is mostly useful in tests, where mutating the real environment
+    /// would race with other tests.
+    pub fn with_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.vars = Some(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        );
+        self
+    }
+
+    fn vars(&self) -> Vec<(String, String)> {
+        match &self.vars {
+            Some(vars) => vars.clone().into_iter().collect(),
+            // Non-Unicode variables cannot belong to us, so skip them
+            // instead of panicking like `std::env::vars` would.
+            None => std::env::vars_os()
+                .filter_map(|(key, value)| {
+                    Some((key.into_string().ok()?, value.into_string().ok()?))
+                })
+                .collect(),
+        }
+    }
+
+    /// Strips the prefix and returns the lowercased key, if the variable
+    /// belongs to this provider.
+    fn key_for(&self, name: &str) -> Option<String> {
+        let prefix_len = self.prefix.len();
+        if name.len() <= prefix_len + 1
+            || !name.is_char_boundary(prefix_len)
+            || !name[..prefix_len].eq_ignore_ascii_case(&self.prefix)
+            || name.as_bytes()[prefix_len] != b'_'
+        {
+            return None;
+        }
+        Some(name[prefix_len + 1..].to_ascii_lowercase())
+    }
+}
+
+impl Default for EnvConfigurationProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn invalid(name: &str, value: &str, expected: &str) -> ConfigError {
+    ConfigError::InvalidEnvVar {
+        name: name.to_string(),
+        value: value.to_string(),
+        expected: expected.to_string(),
+    }
+}
+
+fn parse_log_level(name: &str, value: &str) -> Result<LogLevel, ConfigError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "trace" => Ok(LogLevel::Trace),
+        "debug" => Ok(LogLevel::Debug),
+        "info" => Ok(LogLevel::Info),
+        "warn" => Ok(LogLevel::Warn),
+        "error" => Ok(LogLevel::Error),
+        _ => Err(invalid(
+            name,
+            value,
+            "one of trace, debug, info, warn, error",
+        )),
+    }
+}
+
+fn parse_log_format(name: &str, value: &str) -> Result<LogFormat, ConfigError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        _ => Err(invalid(name, value, "one of text, json")),
+    }
+}
+
+fn parse_bool(name: &str, value: &str) -> Result<bool, ConfigError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(invalid(
+            name,
+            value,
+            "a boolean (true/false, 1/0, yes/no, on/off)",
+        )),
+    }
+}
+
+/// A loaded configuration paired with `(field, variable name)` sources.
+type EnvLayer = (AppConfig, Vec<(String, String)>);
+
+impl EnvConfigurationProvider {
+    /// Loads the configuration along with the variable that set each field.
+    ///
+    /// Returns `Ok(None)` if no variable maps to a known field.
+    fn load_vars(&self) -> Result<Option<EnvLayer>, ConfigError> {
+        let mut config = AppConfig::default();
+        let mut sources = Vec::new();
+
+        for (name, value) in self.vars() {
+            let Some(key) = self.key_for(&name) else {
+                continue;
+            };
+
+            let field = match key.split_once("__") {
+                None => {
+                    match key.as_str() {
+                        "environment" => config.environment = Some(value),
+                        "log_level" => config.log_level = Some(parse_log_level(&name, &value)?),
+                        "log_format" => config.log_format = Some(parse_log_format(&name, &value)?),
+                        "include" => {
+                            let source = format!("environment variable {}", name);
+                            return Err(include_not_supported(&value, &source));
+                        }
+                        _ => continue,
+                    }
+                    key
+                }
+                Some(("feature_flags", flag)) if !flag.is_empty() => {
+                    config
+                        .feature_flags
+                        .insert(flag.to_string(), parse_bool(&name, &value)?.into());
+                    format!("feature_flags.{}", flag)
+                }
+                Some(_) => continue,
+            };
+            sources.push((field, name));
+        }
+
+        if sources.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some((config, sources)))
+        }
+    }
+}
+
+impl ConfigurationProvider for EnvConfigurationProvider {
+    fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        Ok(self.load_vars()?.map(|(config, _)| config))
+    }
+
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        Ok(self.load_vars()?.map(|(config, sources)| {
+            let mut provenance = Provenance::new();
+            for (field, name) in sources {
+                provenance.record(
+                    field,
+                    ValueSource::named(format!("environment variable {}", name)),
+                );
+            }
+            (config, provenance)
+        }))
+    }
+
+    fn name(&self) -> String {
+        "environment variables".to_string()
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        Validator::default().validate(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{layers::LayeredConfigurationProvider, loader::FileConfigurationProvider};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_load_all_fields() {
+        let provider = EnvConfigurationProvider::new().with_vars([
+            ("CIPHR_ENVIRONMENT", "staging"),
+            ("CIPHR_LOG_LEVEL", "DEBUG"),
+            ("CIPHR_LOG_FORMAT", " json "),
+            ("CIPHR_FEATURE_FLAGS__NEW_UI", "true"),
+            ("CIPHR_FEATURE_FLAGS__LEGACY_REPORTS", "off"),
+        ]);
+
+        let config = provider.load().unwrap().unwrap();
+
+        assert_eq!(config.environment, Some("staging".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(config.log_format, Some(LogFormat::Json));
+        assert!(config.feature_flags["new_ui"].is_enabled());
+        assert!(!config.feature_flags["legacy_reports"].is_enabled());
+    }
+
+    #[test]
+    fn test_no_matching_vars() {
+        let provider = EnvConfigurationProvider::new().with_vars([
+            ("HOME", "/home/ciphr"),
+            ("CIPHRX_LOG_LEVEL", "debug"),
+            ("CIPHR_UNKNOWN_SETTING", "value"),
+        ]);
+
+        assert!(provider.load().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_custom_prefix() {
+        let provider = EnvConfigurationProvider::with_prefix("LEDGER")
+            .with_vars([("LEDGER_LOG_LEVEL", "warn"), ("CIPHR_LOG_LEVEL", "trace")]);
+
+        let config = provider.load().unwrap().unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Warn));
+    }
+
+    #[test]
+    fn test_invalid_enum_value() {
+        let provider = EnvConfigurationProvider::new().with_vars([("CIPHR_LOG_LEVEL", "verbose")]);
+
+        let result = provider.load();
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidEnvVar { ref name, ref value, .. })
+                if name == "CIPHR_LOG_LEVEL" && value == "verbose"
+        ));
+    }
+
+    #[test]
+    fn test_invalid_bool_value() {
+        let provider =
+            EnvConfigurationProvider::new().with_vars([("CIPHR_FEATURE_FLAGS__NEW_UI", "maybe")]);
+
+        let result = provider.load();
+        assert!(matches!(result, Err(ConfigError::InvalidEnvVar { .. })));
+    }
+
+    #[test]
+    fn test_include_is_rejected() {
+        let provider = EnvConfigurationProvider::new().with_vars([("CIPHR_INCLUDE", "local.toml")]);
+
+        let result = provider.load();
+        assert!(matches!(
+            result,
+            Err(ConfigError::InvalidInclude { ref pattern, .. }) if pattern == "local.toml"
+        ));
+    }
+
+    #[test]
+    fn test_load_with_provenance_names_variables() {
+        let provider = EnvConfigurationProvider::new().with_vars([
+            ("CIPHR_LOG_LEVEL", "debug"),
+            ("CIPHR_FEATURE_FLAGS__NEW_UI", "true"),
+        ]);
+
+        let (_, provenance) = provider.load_with_provenance().unwrap().unwrap();
+
+        assert_eq!(
+            provenance.get("log_level").unwrap().to_string(),
+            "set by environment variable CIPHR_LOG_LEVEL"
+        );
+        assert_eq!(
+            provenance.get("feature_flags.new_ui").unwrap().source.provider,
+            "environment variable CIPHR_FEATURE_FLAGS__NEW_UI"
+        );
+    }
+
+    #[test]
+    fn test_overrides_file_provider() {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"
+            environment = "development"
+            log_level = "info"
+            [feature_flags]
+            new_ui = false
+            reports = true
+            "#
+        )
+        .unwrap();
+
+        let env_provider = EnvConfigurationProvider::new().with_vars([
+            ("CIPHR_LOG_LEVEL", "error"),
+            ("CIPHR_FEATURE_FLAGS__NEW_UI", "1"),
+        ]);
+
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(file.path())))
+            .with_provider(Box::new(env_provider));
+
+        let config = layered_provider.load().unwrap().unwrap();
+
+        assert_eq!(config.environment, Some("development".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Error));
+        assert!(config.feature_flags["new_ui"].is_enabled());
+        assert!(config.feature_flags["reports"].is_enabled());
+    }
+}
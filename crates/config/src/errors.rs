@@ -1,5 +1,9 @@
+use crate::{strict::UnknownKeys, validation::ValidationErrors};
 use thiserror::Error;
-use std::io;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
 
 #[derive(Error, Debug)]
 pub enum ConfigError {
@@ -9,12 +13,96 @@ pub enum ConfigError {
     #[error("Failed to parse TOML configuration: {0}")]
     Toml(#[from] toml::de::Error),
 
-    #[error("Validation failed: {field}")]
-    ValidationError { field: String },
+    #[error("Failed to parse JSON configuration: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to parse JSON5 configuration: {0}")]
+    Json5(#[from] json5::Error),
+
+    #[error("Failed to parse YAML configuration: {0}")]
+    Yaml(#[from] serde_norway::Error),
+
+    #[error("Validation failed: {0}")]
+    Validation(ValidationErrors),
+
+    #[error("Unknown configuration keys: {0}")]
+    UnknownKeys(UnknownKeys),
+
+    #[error("Invalid value {value:?} for environment variable {name}: expected {expected}")]
+    InvalidEnvVar {
+        name: String,
+        value: String,
+        expected: String,
+    },
+
+    #[error("Invalid secret key: {0}")]
+    InvalidSecretKey(String),
+
+    #[error("Failed to decrypt `{field}`: {reason}")]
+    Decryption { field: String, reason: String },
+
+    #[error("Invalid [{section}] section: {source}")]
+    InvalidSection {
+        section: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    #[error("Failed to interpolate `{field}`: {reason}")]
+    Interpolation { field: String, reason: String },
+
+    #[error("Invalid override `{arg}`: {reason}")]
+    InvalidOverride { arg: String, reason: String },
+
+    #[error("Failed to migrate configuration from version {version}: {reason}")]
+    Migration { version: u32, reason: String },
+
+    #[error("Failed to fetch configuration from {url}: {reason}")]
+    Remote { url: String, reason: String },
+
+    #[error("{}: {source}", path.display())]
+    File {
+        path: PathBuf,
+        #[source]
+        source: Box<ConfigError>,
+    },
+
+    #[error("Include cycle: {}", display_chain(.0))]
+    IncludeCycle(Vec<PathBuf>),
+
+    #[error("Invalid include `{pattern}`: {reason}")]
+    InvalidInclude { pattern: String, reason: String },
+
+    #[error("Failed to serialize configuration: {0}")]
+    Serialization(String),
 
     #[error("Configuration source not found: {0}")]
     NotFound(String),
 
     #[error("An unexpected error occurred: {0}")]
     Unexpected(#[from] anyhow::Error),
-} 
\ No newline at end of file
+}
+
+impl ConfigError {
+    /// Attributes the error to the file at `path`, unless it already names
+    /// a file.
+    pub(crate) fn in_file(self, path: &Path) -> Self {
+        match self {
+            ConfigError::File { .. }
+            | ConfigError::IncludeCycle(_)
+            | ConfigError::UnknownKeys(_) => self,
+            source => ConfigError::File {
+                path: path.to_path_buf(),
+                source: Box::new(source),
+            },
+        }
+    }
+}
+
+fn display_chain(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(" -> ")
+}
@@ -0,0 +1,387 @@
+//! Feature flag definitions.
+//!
+//! A flag is either a bare boolean or a table with rollout and targeting
+//! settings:
+//!
+//! ```toml
+//! [feature_flags]
+//! dark_mode = true
+//!
+//! [feature_flags.new_ui]
+//! description = "The redesigned ledger view"
+//! rollout = 0.25
+//! segments = ["beta"]
+//! ```
+//!
+//! Both forms load into a `FlagDefinition`. A table defines a flag that is
+//! enabled unless it sets `enabled = false`. A table may also target users
+//! by their attributes with a `rule`, see the `rule` module.
+//!
+//! A multivariate flag lists `variants` for experiments, each assigned to a
+//! share of users in proportion to its `weight`:
+//!
+//! ```toml
+//! [feature_flags.checkout_layout]
+//! variants = [
+//!     { value = "single_page", weight = 3 },
+//!     { value = "multi_step", weight = 1 },
+//! ]
+//! ```
+
+use crate::rule::Rule;
+use schemars::{json_schema, JsonSchema, Schema, SchemaGenerator};
+use serde::{
+    de::{self, value::MapAccessDeserializer, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{borrow::Cow, fmt};
+
+/// The definition of a single feature flag.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FlagDefinition {
+    /// Whether the flag is on at all. A disabled flag is off for everyone,
+    /// regardless of `rollout` and `segments`.
+    ///
+    /// `None` if it is not set, as in a table that leaves out `enabled`.
+    /// Such a flag is enabled, see `is_enabled`.
+    pub enabled: Option<bool>,
+    /// What the flag controls.
+    pub description: Option<String>,
+    /// The fraction of users, from `0.0` to `1.0`, the flag is enabled for.
+    /// `None` enables it for everyone.
+    pub rollout: Option<f64>,
+    /// The user segments the flag is limited to. Empty means every segment.
+    pub segments: Vec<String>,
+    /// When the flag was introduced, e.g. `2025-06-01`.
+    pub created_at: Option<String>,
+    /// The attributes a user must have for the flag to be enabled.
+    pub rule: Option<Rule>,
+    /// The values the flag assigns to users, for experiments. Empty for a
+    /// flag that is simply on or off.
+    pub variants: Vec<Variant>,
+}
+
+/// One of the values a multivariate flag can take.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Variant {
+    /// The value: a string, a number or any table or array.
+    pub value: serde_json::Value,
+    /// The share of users assigned this variant, relative to the weights of
+    /// the others. Defaults to `1`.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+impl Variant {
+    /// Creates a variant with the given weight.
+    pub fn new(value: impl Into<serde_json::Value>, weight: u32) -> Self {
+        Self {
+            value: value.into(),
+            weight,
+        }
+    }
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+impl FlagDefinition {
+    /// Creates a flag that is simply on or off.
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled: Some(enabled),
+            ..Default::default()
+        }
+    }
+
+    /// Returns whether the flag is on at all. A flag that does not set
+    /// `enabled` is on.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    /// Returns `true` if the flag only sets `enabled`, so it can be written
+    /// as a bare boolean.
+    pub fn is_shorthand(&self) -> bool {
+        self.description.is_none()
+            && self.rollout.is_none()
+            && self.segments.is_empty()
+            && self.created_at.is_none()
+            && self.rule.is_none()
+            && self.variants.is_empty()
+    }
+
+    /// Overlays `other`, a definition of the same flag from a later layer.
+    ///
+    /// Each setting is taken from `other` only where `other` sets it.
+    /// Overriding a file's table with the `CIPHR_FEATURE_FLAGS__NEW_UI=false`
+    /// shorthand therefore turns the flag off without dropping its rollout,
+    /// and a later table that only sets a `description` keeps the flag
+    /// disabled.
+    ///
+    /// A later layer cannot remove a setting: `segments`, `rollout`, `rule`
+    /// and `variants` stay as an earlier layer set them unless they are
+    /// replaced. To drop one, remove it from the layer that sets it.
+    pub(crate) fn merge(&mut self, other: FlagDefinition) {
+        if other.enabled.is_some() {
+            self.enabled = other.enabled;
+        }
+        if other.description.is_some() {
+            self.description = other.description;
+        }
+        if other.rollout.is_some() {
+            self.rollout = other.rollout;
+        }
+        if !other.segments.is_empty() {
+            self.segments = other.segments;
+        }
+        if other.created_at.is_some() {
+            self.created_at = other.created_at;
+        }
+        if other.rule.is_some() {
+            self.rule = other.rule;
+        }
+        if !other.variants.is_empty() {
+            self.variants = other.variants;
+        }
+    }
+}
+
+impl From<bool> for FlagDefinition {
+    fn from(enabled: bool) -> Self {
+        Self::new(enabled)
+    }
+}
+
+/// Shows shorthand flags as `true` or `false` and others as inline JSON,
+/// e.g. `{"enabled":true,"rollout":0.25}`.
+impl fmt::Display for FlagDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_shorthand() {
+            return write!(f, "{}", self.is_enabled());
+        }
+        let json = serde_json::to_string(&FlagTable::from(self.clone())).map_err(|_| fmt::Error)?;
+        write!(f, "{}", json)
+    }
+}
+
+/// The table form of a flag, as written in configuration files.
+#[derive(Serialize, Deserialize, JsonSchema)]
+struct FlagTable {
+    /// Whether the flag is on. Defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    enabled: Option<bool>,
+    /// What the flag controls.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    /// The fraction of users, from 0.0 to 1.0, the flag is enabled for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rollout: Option<f64>,
+    /// The user segments the flag is limited to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    segments: Vec<String>,
+    /// When the flag was introduced, e.g. `2025-06-01`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    created_at: Option<String>,
+    /// The attributes a user must have for the flag to be enabled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule: Option<Rule>,
+    /// The values the flag assigns to users, with their weights.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    variants: Vec<Variant>,
+}
+
+impl From<FlagDefinition> for FlagTable {
+    fn from(flag: FlagDefinition) -> Self {
+        Self {
+            enabled: flag.enabled,
+            description: flag.description,
+            rollout: flag.rollout,
+            segments: flag.segments,
+            created_at: flag.created_at,
+            rule: flag.rule,
+            variants: flag.variants,
+        }
+    }
+}
+
+impl From<FlagTable> for FlagDefinition {
+    fn from(table: FlagTable) -> Self {
+        Self {
+            enabled: table.enabled,
+            description: table.description,
+            rollout: table.rollout,
+            segments: table.segments,
+            created_at: table.created_at,
+            rule: table.rule,
+            variants: table.variants,
+        }
+    }
+}
+
+impl Serialize for FlagDefinition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.is_shorthand() {
+            serializer.serialize_bool(self.is_enabled())
+        } else {
+            FlagTable::from(self.clone()).serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FlagDefinition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct FlagVisitor;
+
+        impl<'de> Visitor<'de> for FlagVisitor {
+            type Value = FlagDefinition;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a boolean or a flag table")
+            }
+
+            fn visit_bool<E: de::Error>(self, enabled: bool) -> Result<Self::Value, E> {
+                Ok(FlagDefinition::new(enabled))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                FlagTable::deserialize(MapAccessDeserializer::new(map)).map(Into::into)
+            }
+        }
+
+        deserializer.deserialize_any(FlagVisitor)
+    }
+}
+
+impl JsonSchema for FlagDefinition {
+    fn schema_name() -> Cow<'static, str> {
+        "FlagDefinition".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        // The table comes first so that strict mode checks its keys.
+        let table = generator.subschema_for::<FlagTable>();
+        json_schema!({
+            "description": "A feature flag: `true`, `false` or a table with rollout settings.",
+            "anyOf": [table, { "type": "boolean" }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_parses_shorthand_and_tables() {
+        let flags: HashMap<String, FlagDefinition> = toml::from_str(
+            r#"
+            dark_mode = true
+            legacy = false
+
+            [new_ui]
+            rollout = 0.25
+            segments = ["beta"]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(flags["dark_mode"], FlagDefinition::new(true));
+        assert_eq!(flags["legacy"], FlagDefinition::new(false));
+        assert_eq!(
+            flags["new_ui"],
+            FlagDefinition {
+                rollout: Some(0.25),
+                segments: vec!["beta".to_string()],
+                ..Default::default()
+            }
+        );
+        assert!(flags["new_ui"].is_enabled());
+        assert!(!flags["legacy"].is_enabled());
+    }
+
+    #[test]
+    fn test_parses_variants() {
+        let flags: HashMap<String, FlagDefinition> = toml::from_str(
+            r#"
+            [checkout_layout]
+            variants = [
+                { value = "single_page", weight = 3 },
+                { value = { steps = 2 } },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            flags["checkout_layout"].variants,
+            vec![
+                Variant::new("single_page", 3),
+                Variant::new(serde_json::json!({ "steps": 2 }), 1),
+            ]
+        );
+        assert!(!flags["checkout_layout"].is_shorthand());
+    }
+
+    #[test]
+    fn test_reports_invalid_fields() {
+        let error =
+            toml::from_str::<HashMap<String, FlagDefinition>>("[new_ui]\nrollout = \"25%\"\n")
+                .unwrap_err();
+        assert!(error.to_string().contains("invalid type"), "{}", error);
+
+        assert!(toml::from_str::<HashMap<String, FlagDefinition>>("new_ui = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_serializes_shorthand_when_possible() {
+        let flags = HashMap::from([
+            ("dark_mode".to_string(), FlagDefinition::new(true)),
+            (
+                "new_ui".to_string(),
+                FlagDefinition {
+                    enabled: Some(false),
+                    rollout: Some(0.5),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let json = serde_json::to_value(&flags).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "dark_mode": true,
+                "new_ui": { "enabled": false, "rollout": 0.5 },
+            })
+        );
+        assert_eq!(flags["dark_mode"].to_string(), "true");
+        assert_eq!(
+            flags["new_ui"].to_string(),
+            r#"{"enabled":false,"rollout":0.5}"#
+        );
+    }
+
+    #[test]
+    fn test_merge_keeps_unset_settings() {
+        let mut flag = FlagDefinition {
+            enabled: Some(true),
+            rollout: Some(0.25),
+            segments: vec!["beta".to_string()],
+            ..Default::default()
+        };
+        flag.merge(FlagDefinition::new(false));
+
+        assert!(!flag.is_enabled());
+        assert_eq!(flag.rollout, Some(0.25));
+        assert_eq!(flag.segments, vec!["beta".to_string()]);
+
+        flag.merge(FlagDefinition {
+            description: Some("The redesigned ledger view".to_string()),
+            ..Default::default()
+        });
+        assert!(!flag.is_enabled());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::errors::ConfigError;
+use serde::de::{self, DeserializeOwned};
+use std::{fmt, path::Path};
+
+/// The file formats a configuration can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Json,
+    Json5,
+    Yaml,
+}
+
+impl ConfigFormat {
+    /// Detects the format from a file extension.
+    ///
+    /// Recognises `.toml`, `.json`, `.json5`, `.yaml` and `.yml`
+    /// (case-insensitively). Returns `None` for any other extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "toml" => Some(ConfigFormat::Toml),
+            "json" => Some(ConfigFormat::Json),
+            "json5" => Some(ConfigFormat::Json5),
+            "yaml" | "yml" => Some(ConfigFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// Deserializes `contents` in this format.
+    ///
+    /// Parse errors are mapped to the `ConfigError` variant for the format,
+    /// each of which reports the line and column of the problem.
+    pub fn parse<T: DeserializeOwned>(self, contents: &str) -> Result<T, ConfigError> {
+        match self {
+            ConfigFormat::Toml => Ok(toml::from_str(contents)?),
+            ConfigFormat::Json => Ok(serde_json::from_str(contents)?),
+            ConfigFormat::Json5 => Ok(json5::from_str(contents)?),
+            ConfigFormat::Yaml => Ok(serde_norway::from_str(contents)?),
+        }
+    }
+
+    /// Deserializes a document that was parsed from this format and then
+    /// edited, such as one upgraded by a migration.
+    ///
+    /// Errors are mapped to the `ConfigError` variant for the format, like
+    /// `parse`, but have no position since the text was not kept.
+    pub fn from_value<T: DeserializeOwned>(
+        self,
+        value: serde_json::Value,
+    ) -> Result<T, ConfigError> {
+        serde_json::from_value(value).map_err(|error| match self {
+            ConfigFormat::Toml => ConfigError::Toml(de::Error::custom(error)),
+            ConfigFormat::Json => ConfigError::Json(error),
+            ConfigFormat::Json5 => ConfigError::Json5(de::Error::custom(error)),
+            ConfigFormat::Yaml => ConfigError::Yaml(de::Error::custom(error)),
+        })
+    }
+}
+
+impl fmt::Display for ConfigFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigFormat::Toml => write!(f, "toml"),
+            ConfigFormat::Json => write!(f, "json"),
+            ConfigFormat::Json5 => write!(f, "json5"),
+            ConfigFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{AppConfig, LogFormat, LogLevel};
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(
+            ConfigFormat::from_path("ciphr.toml"),
+            Some(ConfigFormat::Toml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path("ciphr.json"),
+            Some(ConfigFormat::Json)
+        );
+        assert_eq!(
+            ConfigFormat::from_path("ciphr.JSON5"),
+            Some(ConfigFormat::Json5)
+        );
+        assert_eq!(
+            ConfigFormat::from_path("ciphr.yaml"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(
+            ConfigFormat::from_path("conf/ciphr.yml"),
+            Some(ConfigFormat::Yaml)
+        );
+        assert_eq!(ConfigFormat::from_path("ciphr.conf"), None);
+        assert_eq!(ConfigFormat::from_path("ciphr"), None);
+    }
+
+    #[test]
+    fn test_parse_each_format() {
+        let expected = AppConfig {
+            environment: Some("production".to_string()),
+            log_level: Some(LogLevel::Debug),
+            log_format: Some(LogFormat::Json),
+            feature_flags: [("new_ui".to_string(), true.into())].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let json = r#"{
+            "environment": "production",
+            "log_level": "debug",
+            "log_format": "json",
+            "feature_flags": { "new_ui": true }
+        }"#;
+        let json5 = r#"{
+            // JSON5 allows comments, unquoted keys and trailing commas.
+            environment: 'production',
+            log_level: "debug",
+            log_format: "json",
+            feature_flags: { new_ui: true, },
+        }"#;
+        let yaml = "
+environment: production
+log_level: debug
+log_format: json
+feature_flags:
+  new_ui: true
+";
+
+        let parsed: AppConfig = ConfigFormat::Json.parse(json).unwrap();
+        assert_eq!(parsed, expected);
+        let parsed: AppConfig = ConfigFormat::Json5.parse(json5).unwrap();
+        assert_eq!(parsed, expected);
+        let parsed: AppConfig = ConfigFormat::Yaml.parse(yaml).unwrap();
+        assert_eq!(parsed, expected);
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let result: Result<AppConfig, _> =
+            ConfigFormat::Json.parse("{\n  \"log_level\": \"debug\",\n  oops\n}");
+        match result {
+            Err(ConfigError::Json(e)) => assert_eq!((e.line(), e.column()), (3, 3)),
+            other => panic!("expected a JSON error, got {:?}", other),
+        }
+
+        let result: Result<AppConfig, _> =
+            ConfigFormat::Yaml.parse("log_level: debug\nfeature_flags: [\n");
+        match result {
+            Err(ConfigError::Yaml(e)) => assert!(e.location().is_some()),
+            other => panic!("expected a YAML error, got {:?}", other),
+        }
+
+        let result: Result<AppConfig, _> = ConfigFormat::Json5.parse("{\n  log_level: }");
+        match result {
+            Err(ConfigError::Json5(json5::Error::Message { location, .. })) => {
+                assert_eq!(location.map(|l| l.line), Some(2))
+            }
+            other => panic!("expected a JSON5 error, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! A shared, atomically swappable configuration.
+//!
+//! Loading a configuration parses every layer, so hot paths should not call
+//! `ConfigurationProvider::load` themselves. Load once into a `ConfigHandle`
+//! instead and read snapshots from it:
+//!
+//! ```
+//! use config::{handle::ConfigHandle, types::AppConfig};
+//!
+//! ConfigHandle::global().store(AppConfig::default());
+//!
+//! let config = ConfigHandle::global().snapshot();
+//! assert_eq!(config.log_level, None);
+//! ```
+
+use crate::{errors::ConfigError, traits::ConfigurationProvider, types::AppConfig};
+use arc_swap::{ArcSwap, Guard};
+use std::sync::{Arc, OnceLock};
+
+static GLOBAL: OnceLock<ConfigHandle> = OnceLock::new();
+
+/// Holds the current configuration and replaces it atomically.
+///
+/// Reads are lock-free and never block a concurrent `store` or `reload`.
+/// A snapshot stays valid and unchanged while it is held; later reads see
+/// the new configuration.
+#[derive(Debug)]
+pub struct ConfigHandle {
+    current: ArcSwap<AppConfig>,
+}
+
+impl ConfigHandle {
+    /// Creates a handle holding `config`.
+    pub fn new(config: AppConfig) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(config),
+        }
+    }
+
+    /// Loads and validates a configuration from `provider`.
+    ///
+    /// If the provider finds no configuration at all, the handle starts
+    /// from `AppConfig::default()`.
+    pub fn from_provider(provider: &dyn ConfigurationProvider) -> Result<Self, ConfigError> {
+        Ok(Self::new(load(provider)?))
+    }
+
+    /// Returns the process-wide handle, which starts out holding
+    /// `AppConfig::default()` until a configuration is stored in it.
+    pub fn global() -> &'static ConfigHandle {
+        GLOBAL.get_or_init(|| ConfigHandle::new(AppConfig::default()))
+    }
+
+    /// Returns the current configuration.
+    ///
+    /// This is the cheapest way to read it and suits short-lived borrows;
+    /// use `get` to keep a snapshot around, e.g. across an `.await`.
+    pub fn snapshot(&self) -> Guard<Arc<AppConfig>> {
+        self.current.load()
+    }
+
+    /// Returns an owned reference to the current configuration.
+    pub fn get(&self) -> Arc<AppConfig> {
+        self.current.load_full()
+    }
+
+    /// Replaces the configuration.
+    pub fn store(&self, config: AppConfig) {
+        self.current.store(Arc::new(config));
+    }
+
+    /// Loads and validates a new configuration from `provider` and replaces
+    /// the current one with it.
+    ///
+    /// If loading or validation fails, the current configuration is kept
+    /// and the error is returned.
+    pub fn reload(&self, provider: &dyn ConfigurationProvider) -> Result<(), ConfigError> {
+        self.store(load(provider)?);
+        Ok(())
+    }
+}
+
+impl Default for ConfigHandle {
+    fn default() -> Self {
+        Self::new(AppConfig::default())
+    }
+}
+
+fn load(provider: &dyn ConfigurationProvider) -> Result<AppConfig, ConfigError> {
+    let config = provider.load()?.unwrap_or_default();
+    provider.validate(&config)?;
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{loader::FileConfigurationProvider, types::LogLevel};
+    use std::fs;
+    use test_utils::mock_fs::create_temp_config_file;
+
+    #[test]
+    fn test_snapshots_survive_reloads() {
+        let file = create_temp_config_file("log_level = \"info\"\n");
+        let provider = FileConfigurationProvider::new(file.path());
+        let handle = ConfigHandle::from_provider(&provider).unwrap();
+
+        let before = handle.get();
+        fs::write(file.path(), "log_level = \"debug\"\n").unwrap();
+        handle.reload(&provider).unwrap();
+
+        assert_eq!(before.log_level, Some(LogLevel::Info));
+        assert_eq!(handle.snapshot().log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_failed_reload_keeps_current() {
+        let file = create_temp_config_file("log_level = \"info\"\n");
+        let provider = FileConfigurationProvider::new(file.path());
+        let handle = ConfigHandle::from_provider(&provider).unwrap();
+
+        fs::write(file.path(), "environment = \"\"\n").unwrap();
+        assert!(matches!(
+            handle.reload(&provider),
+            Err(ConfigError::Validation(_))
+        ));
+        assert_eq!(handle.snapshot().log_level, Some(LogLevel::Info));
+    }
+
+    #[test]
+    fn test_global_is_shared() {
+        assert!(std::ptr::eq(ConfigHandle::global(), ConfigHandle::global()));
+    }
+}
@@ -0,0 +1,370 @@
+//! Substitutes `${...}` references in string values.
+//!
+//! Three kinds of reference are supported:
+//!
+//! | Reference                  | Replaced with                                   |
+//! |----------------------------|-------------------------------------------------|
+//! | `${env:HOME}`              | the value of the `HOME` environment variable    |
+//! | `${file:/run/secrets/db}`  | the contents of the file, minus a final newline |
+//! | `${config:paths.data_dir}` | the value of another field, itself interpolated |
+//!
+//! - Only string values are interpolated: `environment`, secrets and strings
+//!   inside sections (including array elements). Secrets decrypted from
+//!   `enc:v1:` values are used verbatim: a `${` in the plaintext is kept.
+//! - `config` references use the dotted paths of `AppConfig::fields`.
+//!   References that form a cycle are reported with the full chain.
+//! - Only secrets may reference `secrets.*`, so that a secret cannot be
+//!   copied into a field that is printed in clear.
+//! - `$${` produces a literal `${`.
+
+use crate::{errors::ConfigError, types::AppConfig};
+use serde_json::Value;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+};
+
+/// Resolves `${...}` references in a merged configuration.
+///
+/// `LayeredConfigurationProvider` runs the default interpolator once all of
+/// its layers are merged.
+#[derive(Debug, Clone, Default)]
+pub struct Interpolator {
+    vars: Option<HashMap<String, String>>,
+}
+
+impl Interpolator {
+    /// Creates an interpolator that reads `env` references from the process
+    /// environment.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolves `env` references from the given set instead of the process
+    /// environment.
+    pub fn with_vars<I, K, V>(mut self, vars: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<String>,
+    {
+        self.vars = Some(
+            vars.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        );
+        self
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        match &self.vars {
+            Some(vars) => vars.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        }
+    }
+
+    /// Replaces every reference in `config` with its value.
+    pub fn interpolate(&self, config: &mut AppConfig) -> Result<(), ConfigError> {
+        let mut resolver = self.resolver(config);
+
+        if config.environment.is_some() {
+            config.environment = Some(resolver.resolve_field("environment")?);
+        }
+        let secret_names: Vec<String> = config
+            .secrets
+            .iter()
+            .filter(|(_, value)| !value.is_decrypted())
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in secret_names {
+            let value = resolver.resolve_field(&format!("secrets.{}", name))?;
+            config.secrets.insert(name, value.into());
+        }
+        for (name, value) in config.sections.iter_mut() {
+            resolver.resolve_value(name.clone(), value)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the interpolated value of a single field of `config`, which
+    /// must be set.
+    pub(crate) fn resolve(&self, config: &AppConfig, field: &str) -> Result<String, ConfigError> {
+        self.resolver(config).resolve_field(field)
+    }
+
+    fn resolver(&self, config: &AppConfig) -> Resolver<'_> {
+        let mut raw: BTreeMap<String, String> = config.fields().into_iter().collect();
+        let mut resolved = HashMap::new();
+        for (name, value) in &config.secrets {
+            let field = format!("secrets.{}", name);
+            if value.is_decrypted() {
+                resolved.insert(field.clone(), value.expose().clone());
+            }
+            raw.insert(field, value.expose().clone());
+        }
+        Resolver {
+            interpolator: self,
+            raw,
+            resolved,
+            stack: Vec::new(),
+        }
+    }
+}
+
+struct Resolver<'a> {
+    interpolator: &'a Interpolator,
+    raw: BTreeMap<String, String>,
+    resolved: HashMap<String, String>,
+    /// The `config` references currently being resolved, outermost first.
+    stack: Vec<String>,
+}
+
+impl Resolver<'_> {
+    fn resolve_field(&mut self, field: &str) -> Result<String, ConfigError> {
+        if let Some(value) = self.resolved.get(field) {
+            return Ok(value.clone());
+        }
+        // Callers only ask for fields that are set.
+        let raw = self.raw.get(field).cloned().unwrap_or_default();
+
+        self.stack.push(field.to_string());
+        let value = self.expand(field, &raw);
+        self.stack.pop();
+
+        let value = value?;
+        self.resolved.insert(field.to_string(), value.clone());
+        Ok(value)
+    }
+
+    /// Interpolates every string inside a section value in place.
+    fn resolve_value(&mut self, path: String, value: &mut Value) -> Result<(), ConfigError> {
+        match value {
+            // Leaves with a dotted path can be referenced, so resolve them
+            // through the cache; array elements cannot.
+            Value::String(s) if self.raw.contains_key(&path) => {
+                *s = self.resolve_field(&path)?;
+            }
+            Value::String(s) => *s = self.expand(&path, s)?,
+            Value::Object(table) => {
+                for (key, value) in table.iter_mut() {
+                    self.resolve_value(format!("{}.{}", path, key), value)?;
+                }
+            }
+            Value::Array(items) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    self.resolve_value(format!("{}[{}]", path, index), item)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn expand(&mut self, field: &str, text: &str) -> Result<String, ConfigError> {
+        let mut output = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find('$') {
+            output.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            if let Some(after) = rest.strip_prefix("$${") {
+                output.push_str("${");
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix("${") {
+                let end = after
+                    .find('}')
+                    .ok_or_else(|| error(field, "unterminated `${` reference".to_string()))?;
+                output.push_str(&self.reference(field, &after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                output.push('$');
+                rest = &rest[1..];
+            }
+        }
+        output.push_str(rest);
+        Ok(output)
+    }
+
+    fn reference(&mut self, field: &str, reference: &str) -> Result<String, ConfigError> {
+        match reference.split_once(':') {
+            Some(("env", name)) => self
+                .interpolator
+                .var(name)
+                .ok_or_else(|| error(field, format!("environment variable `{}` is not set", name))),
+            Some(("file", path)) => fs::read_to_string(path)
+                .map(|contents| {
+                    let contents = contents.strip_suffix('\n').unwrap_or(&contents);
+                    contents.strip_suffix('\r').unwrap_or(contents).to_string()
+                })
+                .map_err(|e| error(field, format!("cannot read `{}`: {}", path, e))),
+            Some(("config", target)) => {
+                if target.starts_with("secrets.") && !field.starts_with("secrets.") {
+                    return Err(error(
+                        field,
+                        format!(
+                            "`{}` is a secret and can only be referenced by secrets",
+                            target
+                        ),
+                    ));
+                }
+                if !self.raw.contains_key(target) {
+                    return Err(error(field, format!("`{}` is not set", target)));
+                }
+                if let Some(position) = self.stack.iter().position(|f| f == target) {
+                    let mut cycle = self.stack[position..].to_vec();
+                    cycle.push(target.to_string());
+                    return Err(error(
+                        field,
+                        format!("reference cycle: {}", cycle.join(" -> ")),
+                    ));
+                }
+                self.resolve_field(target)
+            }
+            _ => Err(error(
+                field,
+                format!(
+                    "unknown reference `${{{}}}`; expected `env:`, `file:` or `config:`",
+                    reference
+                ),
+            )),
+        }
+    }
+}
+
+fn error(field: &str, reason: String) -> ConfigError {
+    ConfigError::Interpolation {
+        field: field.to_string(),
+        reason,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::{decrypt_secrets, SecretKey};
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn config(toml: &str) -> AppConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn reason_of(result: Result<(), ConfigError>) -> (String, String) {
+        match result {
+            Err(ConfigError::Interpolation { field, reason }) => (field, reason),
+            other => panic!("expected an interpolation error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_resolves_env_file_and_config_references() {
+        let mut secret_file = NamedTempFile::new().unwrap();
+        writeln!(secret_file, "pa55").unwrap();
+        let mut config = config(&format!(
+            r#"
+            environment = "${{env:DEPLOY_ENV}}"
+            [paths]
+            data_dir = "${{env:HOME}}/ciphr"
+            ledger = "${{config:paths.data_dir}}/ledger.journal"
+            backups = ["${{config:paths.data_dir}}/backups", "$${{literal}}"]
+            [secrets]
+            db_password = "${{file:{}}}"
+            "#,
+            secret_file.path().display()
+        ));
+
+        Interpolator::new()
+            .with_vars([("HOME", "/home/ciphr"), ("DEPLOY_ENV", "staging")])
+            .interpolate(&mut config)
+            .unwrap();
+
+        assert_eq!(config.environment.as_deref(), Some("staging"));
+        assert_eq!(
+            config.sections["paths"],
+            serde_json::json!({
+                "data_dir": "/home/ciphr/ciphr",
+                "ledger": "/home/ciphr/ciphr/ledger.journal",
+                "backups": ["/home/ciphr/ciphr/backups", "${literal}"],
+            })
+        );
+        assert_eq!(config.secrets["db_password"].expose(), "pa55");
+    }
+
+    #[test]
+    fn test_keeps_decrypted_secrets_verbatim() {
+        let key = SecretKey::generate();
+        let mut config = config(&format!(
+            r#"
+            [secrets]
+            db_password = "{}"
+            api_token = "{}"
+            dsn = "postgres://app:${{config:secrets.db_password}}@db"
+            "#,
+            key.encrypt("pa${env:HOME}55"),
+            key.encrypt("$${literal}"),
+        ));
+        decrypt_secrets(&mut config, Some(&key)).unwrap();
+
+        Interpolator::new()
+            .with_vars([("HOME", "/home/ciphr")])
+            .interpolate(&mut config)
+            .unwrap();
+
+        assert_eq!(config.secrets["db_password"].expose(), "pa${env:HOME}55");
+        assert_eq!(config.secrets["api_token"].expose(), "$${literal}");
+        assert_eq!(
+            config.secrets["dsn"].expose(),
+            "postgres://app:pa${env:HOME}55@db"
+        );
+    }
+
+    #[test]
+    fn test_reports_cycles() {
+        let mut config = config(
+            r#"
+            [paths]
+            a = "${config:paths.b}"
+            b = "x/${config:paths.c}"
+            c = "${config:paths.a}"
+            "#,
+        );
+
+        let (field, reason) = reason_of(Interpolator::new().interpolate(&mut config));
+        assert_eq!(field, "paths.c");
+        assert_eq!(
+            reason,
+            "reference cycle: paths.a -> paths.b -> paths.c -> paths.a"
+        );
+    }
+
+    #[test]
+    fn test_reports_unresolved_references() {
+        let cases = [
+            (
+                "[paths]\nx = \"${env:MISSING}\"",
+                "environment variable `MISSING` is not set",
+            ),
+            ("[paths]\nx = \"${config:paths.y}\"", "`paths.y` is not set"),
+            (
+                "[paths]\nx = \"${vault:db}\"",
+                "unknown reference `${vault:db}`; expected `env:`, `file:` or `config:`",
+            ),
+            ("[paths]\nx = \"${env:HOME\"", "unterminated `${` reference"),
+            (
+                "[paths]\nx = \"${config:secrets.db}\"\n[secrets]\ndb = \"pa55\"",
+                "`secrets.db` is a secret and can only be referenced by secrets",
+            ),
+        ];
+
+        for (toml, expected) in cases {
+            let mut config = config(toml);
+            let (field, reason) = reason_of(
+                Interpolator::new()
+                    .with_vars([("HOME", "/")])
+                    .interpolate(&mut config),
+            );
+            assert_eq!(field, "paths.x");
+            assert_eq!(reason, expected);
+        }
+    }
+}
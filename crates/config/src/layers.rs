@@ -1,16 +1,34 @@
 use crate::{
+    args::ArgsConfigurationProvider,
     errors::ConfigError,
+    interpolate::Interpolator,
+    provenance::Provenance,
+    section::merge_values,
     traits::ConfigurationProvider,
     types::AppConfig,
+    validation::Validator,
 };
+use std::path::PathBuf;
+#[cfg(feature = "remote")]
+use {crate::traits::AsyncConfigurationProvider, async_trait::async_trait, std::sync::Arc};
 
 /// A configuration provider that layers multiple providers.
 ///
 /// It iterates through its list of providers, loading and merging
 /// the configuration from each one. Later providers in the list
-/// override earlier ones.
+/// override earlier ones. Validation runs against the merged result.
+///
+/// Once all layers are merged, `${env:…}`, `${file:…}` and `${config:…}`
+/// references in string values are resolved (see the `interpolate` module),
+/// so a reference may point at a value set by any layer.
+///
+/// Command-line overrides given to `with_overrides` are applied after every
+/// other layer, however the layers were added.
 pub struct LayeredConfigurationProvider {
     providers: Vec<Box<dyn ConfigurationProvider>>,
+    overrides: Option<ArgsConfigurationProvider>,
+    validator: Validator,
+    interpolator: Option<Interpolator>,
 }
 
 impl LayeredConfigurationProvider {
@@ -18,6 +36,9 @@ impl LayeredConfigurationProvider {
     pub fn new() -> Self {
         Self {
             providers: Vec::new(),
+            overrides: None,
+            validator: Validator::default(),
+            interpolator: Some(Interpolator::new()),
         }
     }
 
@@ -26,6 +47,52 @@ impl LayeredConfigurationProvider {
         self.providers.push(provider);
         self
     }
+
+    /// Sets the `--set` overrides, which take precedence over every other
+    /// layer. Replaces any overrides set before.
+    pub fn with_overrides(mut self, overrides: ArgsConfigurationProvider) -> Self {
+        self.overrides = Some(overrides);
+        self
+    }
+
+    /// Replaces the rules used to validate the merged configuration.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Replaces the interpolator run after merging.
+    pub fn with_interpolator(mut self, interpolator: Interpolator) -> Self {
+        self.interpolator = Some(interpolator);
+        self
+    }
+
+    /// Leaves `${...}` references in the merged configuration untouched.
+    pub fn without_interpolation(mut self) -> Self {
+        self.interpolator = None;
+        self
+    }
+
+    fn interpolate(&self, config: &mut AppConfig) -> Result<(), ConfigError> {
+        match &self.interpolator {
+            Some(interpolator) => interpolator.interpolate(config),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the layers in load order: the providers, then the overrides.
+    fn layers(&self) -> impl Iterator<Item = &dyn ConfigurationProvider> + '_ {
+        let overrides = self
+            .overrides
+            .iter()
+            .map(|overrides| overrides as &dyn ConfigurationProvider);
+        self.providers
+            .iter()
+            .map(|provider| provider.as_ref())
+            .chain(overrides)
+    }
 }
 
 impl Default for LayeredConfigurationProvider {
@@ -43,38 +110,206 @@ macro_rules! merge_option {
     };
 }
 
+/// Merges `loaded_config` into `merged_config`, letting values that are set
+/// in `loaded_config` take precedence.
+pub(crate) fn merge_config(merged_config: &mut AppConfig, loaded_config: AppConfig) {
+    merge_option!(merged_config.config_version, loaded_config.config_version);
+    merge_option!(merged_config.environment, loaded_config.environment);
+    merge_option!(merged_config.log_level, loaded_config.log_level);
+    merge_option!(merged_config.log_format, loaded_config.log_format);
+    for (name, flag) in loaded_config.feature_flags {
+        match merged_config.feature_flags.get_mut(&name) {
+            Some(existing) => existing.merge(flag),
+            None => {
+                merged_config.feature_flags.insert(name, flag);
+            }
+        }
+    }
+    merged_config.secrets.extend(loaded_config.secrets);
+    for (name, section) in loaded_config.sections {
+        match merged_config.sections.get_mut(&name) {
+            Some(existing) => merge_values(existing, section),
+            None => {
+                merged_config.sections.insert(name, section);
+            }
+        }
+    }
+}
+
 impl ConfigurationProvider for LayeredConfigurationProvider {
     fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
         let mut merged_config = AppConfig::default();
         let mut at_least_one_config_loaded = false;
 
-        for provider in &self.providers {
+        for provider in self.layers() {
             if let Some(loaded_config) = provider.load()? {
                 at_least_one_config_loaded = true;
-                merge_option!(merged_config.environment, loaded_config.environment);
-                merge_option!(merged_config.log_level, loaded_config.log_level);
-                merge_option!(merged_config.log_format, loaded_config.log_format);
-                merged_config.feature_flags.extend(loaded_config.feature_flags);
+                merge_config(&mut merged_config, loaded_config);
             }
         }
 
         if at_least_one_config_loaded {
+            self.interpolate(&mut merged_config)?;
             Ok(Some(merged_config))
         } else {
             Ok(None)
         }
     }
 
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        let mut merged = None;
+
+        for provider in self.layers() {
+            if let Some((loaded_config, loaded_provenance)) = provider.load_with_provenance()? {
+                let (merged_config, merged_provenance) =
+                    merged.get_or_insert_with(|| (AppConfig::default(), Provenance::new()));
+                merge_config(merged_config, loaded_config);
+                merged_provenance.merge(loaded_provenance);
+            }
+        }
+
+        if let Some((merged_config, _)) = &mut merged {
+            self.interpolate(merged_config)?;
+        }
+        Ok(merged)
+    }
+
+    fn name(&self) -> String {
+        "layered".to_string()
+    }
+
     fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
-        // Validation can be complex in a layered setup. For now, we'll
-        // delegate to the first provider's validation logic, assuming
-        // it contains the base validation rules. A more robust strategy
-        // could be implemented later.
-        if let Some(provider) = self.providers.first() {
-            provider.validate(config)
-        } else {
-            Ok(())
+        // Individual layers are often incomplete on their own, so the rules
+        // run once against the merged configuration rather than per layer.
+        self.validator.validate(config)
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        let mut paths = Vec::new();
+        for path in self.layers().flat_map(|p| p.watch_paths()) {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
         }
+        paths
+    }
+}
+
+#[cfg(feature = "remote")]
+enum Layer {
+    Sync(Arc<dyn ConfigurationProvider>),
+    Async(Box<dyn AsyncConfigurationProvider>),
+}
+
+/// The asynchronous counterpart of `LayeredConfigurationProvider`.
+///
+/// It accepts both local providers and remote ones such as
+/// `HttpConfigurationProvider`, loading them in order and merging them with
+/// the same rules, interpolation and validation as the synchronous version.
+/// Synchronous providers are loaded on tokio's blocking thread pool, so
+/// reading files does not stall the runtime.
+///
+/// Requires the `remote` feature.
+#[cfg(feature = "remote")]
+pub struct AsyncLayeredConfigurationProvider {
+    layers: Vec<Layer>,
+    overrides: Option<Layer>,
+    validator: Validator,
+    interpolator: Option<Interpolator>,
+}
+
+#[cfg(feature = "remote")]
+impl AsyncLayeredConfigurationProvider {
+    /// Creates a new, empty layered provider.
+    pub fn new() -> Self {
+        Self {
+            layers: Vec::new(),
+            overrides: None,
+            validator: Validator::default(),
+            interpolator: Some(Interpolator::new()),
+        }
+    }
+
+    /// Adds a synchronous provider, such as a file, to the layer stack.
+    pub fn with_provider(mut self, provider: Box<dyn ConfigurationProvider>) -> Self {
+        self.layers.push(Layer::Sync(Arc::from(provider)));
+        self
+    }
+
+    /// Adds an asynchronous provider to the layer stack.
+    pub fn with_async_provider(mut self, provider: Box<dyn AsyncConfigurationProvider>) -> Self {
+        self.layers.push(Layer::Async(provider));
+        self
+    }
+
+    /// Sets the `--set` overrides, which take precedence over every other
+    /// layer. Replaces any overrides set before.
+    pub fn with_overrides(mut self, overrides: ArgsConfigurationProvider) -> Self {
+        self.overrides = Some(Layer::Sync(Arc::new(overrides)));
+        self
+    }
+
+    /// Replaces the rules used to validate the merged configuration.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Replaces the interpolator run after merging.
+    pub fn with_interpolator(mut self, interpolator: Interpolator) -> Self {
+        self.interpolator = Some(interpolator);
+        self
+    }
+
+    /// Leaves `${...}` references in the merged configuration untouched.
+    pub fn without_interpolation(mut self) -> Self {
+        self.interpolator = None;
+        self
+    }
+}
+
+#[cfg(feature = "remote")]
+impl Default for AsyncLayeredConfigurationProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "remote")]
+#[async_trait]
+impl AsyncConfigurationProvider for AsyncLayeredConfigurationProvider {
+    async fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        let mut merged = None;
+
+        for layer in self.layers.iter().chain(&self.overrides) {
+            let loaded = match layer {
+                Layer::Sync(provider) => {
+                    let provider = Arc::clone(provider);
+                    tokio::task::spawn_blocking(move || provider.load())
+                        .await
+                        .map_err(anyhow::Error::from)??
+                }
+                Layer::Async(provider) => provider.load().await?,
+            };
+            if let Some(loaded_config) = loaded {
+                merge_config(merged.get_or_insert_with(AppConfig::default), loaded_config);
+            }
+        }
+
+        if let (Some(config), Some(interpolator)) = (&mut merged, &self.interpolator) {
+            interpolator.interpolate(config)?;
+        }
+        Ok(merged)
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        self.validator.validate(config)
+    }
+
+    fn name(&self) -> String {
+        "layered".to_string()
     }
 }
 
@@ -83,7 +318,9 @@ mod tests {
     use super::*;
     use crate::{
         loader::FileConfigurationProvider,
+        provenance::ValueSource,
         types::{LogFormat, LogLevel},
+        validation::{KebabCaseFlagNames, NoTraceInProduction},
     };
     use std::io::Write;
     use tempfile::NamedTempFile;
@@ -145,9 +382,231 @@ mod tests {
         
         let config = layered_provider.load().unwrap().unwrap();
 
-        assert_eq!(config.feature_flags.get("one"), Some(&true));
-        assert_eq!(config.feature_flags.get("two"), Some(&true));
-        assert_eq!(config.feature_flags.get("three"), Some(&true));
+        assert!(config.feature_flags["one"].is_enabled());
+        assert!(config.feature_flags["two"].is_enabled());
+        assert!(config.feature_flags["three"].is_enabled());
         assert_eq!(config.feature_flags.len(), 3);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_flag_tables_are_merged() {
+        let base_file = create_temp_config_file(
+            r#"
+            [feature_flags.new_ui]
+            rollout = 0.25
+            segments = ["beta"]
+            "#,
+        );
+        let override_file = create_temp_config_file(
+            r#"
+            [feature_flags]
+            new_ui = false
+            "#,
+        );
+
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base_file.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(override_file.path())));
+        let config = layered_provider.load().unwrap().unwrap();
+
+        let flag = &config.feature_flags["new_ui"];
+        assert!(!flag.is_enabled());
+        assert_eq!(flag.rollout, Some(0.25));
+        assert_eq!(flag.segments, vec!["beta".to_string()]);
+    }
+
+    #[test]
+    fn test_load_with_provenance() {
+        let base_file = create_temp_config_file(
+            "environment = \"development\"\nlog_level = \"info\"\n[feature_flags]\none = true\n",
+        );
+        let override_file = create_temp_config_file(
+            "\n\nlog_level = \"debug\"\n[feature_flags]\ntwo = true\n",
+        );
+
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base_file.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(override_file.path())));
+
+        let (config, provenance) = layered_provider.load_with_provenance().unwrap().unwrap();
+        assert_eq!(config, layered_provider.load().unwrap().unwrap());
+
+        let log_level = provenance.get("log_level").unwrap();
+        assert_eq!(log_level.source.path.as_deref(), Some(override_file.path()));
+        assert_eq!(log_level.source.line, Some(3));
+        assert_eq!(log_level.overridden.len(), 1);
+        assert_eq!(log_level.overridden[0].path.as_deref(), Some(base_file.path()));
+        assert_eq!(log_level.overridden[0].line, Some(2));
+
+        let environment = provenance.get("environment").unwrap();
+        assert_eq!(environment.source.path.as_deref(), Some(base_file.path()));
+        assert!(environment.overridden.is_empty());
+
+        assert_eq!(
+            provenance.get("feature_flags.two").unwrap().source.path.as_deref(),
+            Some(override_file.path())
+        );
+        assert!(provenance.get("log_format").is_none());
+    }
+
+    #[test]
+    fn test_overrides_apply_last() {
+        let file = create_temp_config_file("environment = \"staging\"\nlog_level = \"info\"\n");
+        let overrides = ArgsConfigurationProvider::new(["log_level=debug"]).unwrap();
+
+        // Providers added after the overrides still load before them.
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_overrides(overrides)
+            .with_provider(Box::new(FileConfigurationProvider::new(file.path())));
+
+        let (config, provenance) = layered_provider.load_with_provenance().unwrap().unwrap();
+        assert_eq!(config.environment, Some("staging".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(
+            provenance.get("log_level").unwrap().source,
+            ValueSource::named("command-line argument --set log_level=debug")
+        );
+        assert_eq!(layered_provider.load().unwrap().unwrap(), config);
+    }
+
+    #[test]
+    fn test_validate_merged_config() {
+        let base_file = create_temp_config_file(r#"environment = "production""#);
+        let override_file = create_temp_config_file(
+            r#"
+            log_level = "trace"
+            [feature_flags]
+            New_UI = true
+            "#,
+        );
+
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base_file.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(override_file.path())))
+            .with_validator(
+                Validator::default()
+                    .with_rule(NoTraceInProduction)
+                    .with_rule(KebabCaseFlagNames),
+            );
+
+        let config = layered_provider.load().unwrap().unwrap();
+        let Err(ConfigError::Validation(errors)) = layered_provider.validate(&config) else {
+            panic!("expected validation to fail");
+        };
+
+        let fields: Vec<&str> = errors.failures().iter().map(|f| f.field.as_str()).collect();
+        assert_eq!(fields, vec!["log_level", "feature_flags.New_UI"]);
+    }
+
+    #[test]
+    fn test_load_with_provenance_no_layers() {
+        let layered_provider = LayeredConfigurationProvider::new();
+        assert!(layered_provider.load_with_provenance().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_sections_are_deep_merged() {
+        let base_file = create_temp_config_file(
+            r#"
+            [monitoring]
+            interval_secs = 60
+            [monitoring.http]
+            host = "localhost"
+            port = 80
+            "#,
+        );
+        let override_file = create_temp_config_file(
+            r#"
+            [monitoring.http]
+            port = 9090
+            "#,
+        );
+
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base_file.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(override_file.path())));
+        let (config, provenance) = layered_provider.load_with_provenance().unwrap().unwrap();
+
+        assert_eq!(
+            config.sections["monitoring"],
+            serde_json::json!({
+                "interval_secs": 60,
+                "http": { "host": "localhost", "port": 9090 },
+            })
+        );
+        let port = provenance.get("monitoring.http.port").unwrap();
+        assert_eq!(port.source.path.as_deref(), Some(override_file.path()));
+        assert_eq!(port.overridden.len(), 1);
+    }
+
+    #[test]
+    fn test_interpolates_after_merging() {
+        let base_file = create_temp_config_file(
+            r#"
+            [paths]
+            data_dir = "${env:CIPHR_HOME}/data"
+            ledger = "${config:paths.data_dir}/ledger.journal"
+            "#,
+        );
+        let override_file = create_temp_config_file(
+            r#"
+            [paths]
+            data_dir = "/srv/ciphr"
+            "#,
+        );
+
+        let layered_provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base_file.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(override_file.path())));
+        let config = layered_provider.load().unwrap().unwrap();
+        assert_eq!(
+            config.sections["paths"]["ledger"],
+            "/srv/ciphr/ledger.journal"
+        );
+
+        let raw = layered_provider.without_interpolation().load().unwrap().unwrap();
+        assert_eq!(
+            raw.sections["paths"]["ledger"],
+            "${config:paths.data_dir}/ledger.journal"
+        );
+    }
+
+    #[cfg(feature = "remote")]
+    #[tokio::test]
+    async fn test_async_layers_merge_remote_config() {
+        use crate::remote::HttpConfigurationProvider;
+        use wiremock::{matchers::method, Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string(
+                    "environment = \"production\"\n[feature_flags]\nnew-ui = true\n",
+                ),
+            )
+            .mount(&server)
+            .await;
+        let base_file = create_temp_config_file(
+            r#"
+            environment = "development"
+            log_level = "info"
+            "#,
+        );
+
+        let layered_provider = AsyncLayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base_file.path())))
+            .with_async_provider(Box::new(HttpConfigurationProvider::new(server.uri())));
+        let config = layered_provider.load().await.unwrap().unwrap();
+
+        assert_eq!(config.environment, Some("production".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Info));
+        assert!(config.feature_flags["new-ui"].is_enabled());
+        assert!(layered_provider.validate(&config).is_ok());
+
+        let layered_provider = AsyncLayeredConfigurationProvider::new()
+            .with_overrides(ArgsConfigurationProvider::new(["environment=test"]).unwrap())
+            .with_async_provider(Box::new(HttpConfigurationProvider::new(server.uri())));
+        let config = layered_provider.load().await.unwrap().unwrap();
+        assert_eq!(config.environment, Some("test".to_string()));
+    }
+}
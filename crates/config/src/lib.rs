@@ -4,9 +4,32 @@
 //! It includes definitions for core configuration types, error handling, and
 //! traits for implementing various configuration providers.
 
+pub mod args;
 pub mod builder;
+pub mod diagnostic;
+pub mod diff;
+pub mod directory;
+pub mod env;
 pub mod errors;
+pub mod flag;
+pub mod format;
+pub mod handle;
+pub mod interpolate;
 pub mod layers;
 pub mod loader;
+pub mod migration;
+pub mod profile;
+pub mod provenance;
+#[cfg(feature = "remote")]
+pub mod remote;
+pub mod render;
+pub mod rule;
+pub mod schema;
+pub mod secret;
+pub mod section;
+pub mod strict;
 pub mod traits;
 pub mod types;
+pub mod validation;
+pub mod watcher;
+pub mod writer;
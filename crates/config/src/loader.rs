@@ -1,16 +1,44 @@
 use crate::{
     errors::ConfigError,
+    format::ConfigFormat,
+    layers::merge_config,
+    migration::MigrationRegistry,
+    provenance::{Provenance, ValueSource},
+    schema::app_config_schema,
+    secret::{decrypt_secrets, SecretKey},
+    strict::{unknown_keys, UnknownKey, UnknownKeys},
     traits::ConfigurationProvider,
-    types::{AppConfig},
+    types::AppConfig,
+    validation::Validator,
 };
+use serde::Deserialize;
 use std::{
     fs, io,
+    ops::Range,
     path::{Path, PathBuf},
 };
 
-/// A configuration provider that loads settings from a TOML file.
+/// A configuration provider that loads settings from a file.
+///
+/// The format is detected from the file extension (see
+/// `ConfigFormat::from_path`) unless set explicitly with `with_format`.
+/// Files with an unrecognised extension are read as TOML.
+///
+/// Files written for an older `config_version` are upgraded in memory; see
+/// the `migration` module. Encrypted values in the `[secrets]` table are
+/// decrypted at load time; see the `secret` module for how the key is found.
+///
+/// A file can pull in others with `include = ["conf.d/*.toml"]`. Each entry
+/// is a path or glob pattern relative to the including file, and the files
+/// it matches are loaded in lexical order on top of it. Patterns that match
+/// nothing are ignored.
 pub struct FileConfigurationProvider {
     path: PathBuf,
+    format: Option<ConfigFormat>,
+    validator: Validator,
+    secret_key: Option<SecretKey>,
+    strict_schema: Option<serde_json::Value>,
+    migrations: MigrationRegistry,
 }
 
 impl FileConfigurationProvider {
@@ -21,31 +49,344 @@ impl FileConfigurationProvider {
     pub fn new(path: impl AsRef<Path>) -> Self {
         Self {
             path: path.as_ref().to_path_buf(),
+            format: None,
+            validator: Validator::default(),
+            secret_key: None,
+            strict_schema: None,
+            migrations: MigrationRegistry::default(),
         }
     }
-}
 
-impl ConfigurationProvider for FileConfigurationProvider {
-    fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
-        match fs::read_to_string(&self.path) {
-            Ok(contents) => {
-                let config: AppConfig = toml::from_str(&contents)?;
-                Ok(Some(config))
+    /// Forces the file to be parsed as `format`, regardless of its extension.
+    pub fn with_format(mut self, format: ConfigFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Returns the format the file will be parsed as.
+    pub fn format(&self) -> ConfigFormat {
+        self.format
+            .or_else(|| ConfigFormat::from_path(&self.path))
+            .unwrap_or(ConfigFormat::Toml)
+    }
+
+    /// Replaces the rules used by `validate`.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Sets the key used to decrypt secrets, instead of reading it from the
+    /// environment.
+    pub fn with_secret_key(mut self, key: SecretKey) -> Self {
+        self.secret_key = Some(key);
+        self
+    }
+
+    /// Rejects keys that are not part of `AppConfig`, reporting each with
+    /// its line and the closest known key.
+    ///
+    /// Top-level tables other than the built-in fields are unknown in strict
+    /// mode; use `with_strict_schema` to allow the sections of other crates.
+    pub fn strict(self) -> Self {
+        self.with_strict_schema(app_config_schema())
+    }
+
+    /// Like `strict`, but checks keys against the given schema, usually
+    /// built with `SchemaBuilder::with_section`.
+    pub fn with_strict_schema(mut self, schema: serde_json::Value) -> Self {
+        self.strict_schema = Some(schema);
+        self
+    }
+
+    /// Replaces the migrations applied to files with an older
+    /// `config_version`.
+    ///
+    /// Defaults to `MigrationRegistry::default()`.
+    pub fn with_migrations(mut self, migrations: MigrationRegistry) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    /// Fails with `ConfigError::UnknownKeys` if strict mode is enabled and
+    /// the file contains keys the schema does not describe.
+    fn check_unknown_keys(
+        &self,
+        document: &serde_json::Value,
+        contents: &str,
+    ) -> Result<(), ConfigError> {
+        let Some(schema) = &self.strict_schema else {
+            return Ok(());
+        };
+        let format = self.format();
+
+        let mut unknown: Vec<UnknownKey> = unknown_keys(document, schema)
+            .into_iter()
+            .map(|(key, suggestion)| UnknownKey {
+                line: match format {
+                    ConfigFormat::Toml => key_line(contents, &key),
+                    _ => None,
+                },
+                key,
+                path: self.path.clone(),
+                suggestion,
+            })
+            .collect();
+        unknown.sort_by_key(|key| key.line);
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::UnknownKeys(UnknownKeys(unknown)))
+        }
+    }
+
+    /// Parses the file contents, upgrading them if they were written for an
+    /// older `config_version`, and decrypts any encrypted secrets.
+    fn parse(&self, contents: &str) -> Result<AppConfig, ConfigError> {
+        let format = self.format();
+        let mut document: serde_json::Value = format.parse(contents)?;
+        let mut config: AppConfig = match self.migrations.migrate(&mut document)? {
+            Some(migrated) => {
+                tracing::warn!(
+                    path = %self.path.display(),
+                    from = migrated.from,
+                    to = migrated.to,
+                    "configuration file uses an old layout and was upgraded in memory; \
+                     run `ciphr config migrate` to update it"
+                );
+                format.from_value(document.clone())?
             }
+            // Parsing the text again keeps the line numbers in errors.
+            None => format.parse(contents)?,
+        };
+        self.check_unknown_keys(&document, contents)?;
+        decrypt_secrets(&mut config, self.secret_key.as_ref())?;
+        Ok(config)
+    }
+
+    /// Reads the file, returning `Ok(None)` if it does not exist.
+    fn read(&self) -> Result<Option<String>, ConfigError> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(Some(contents)),
             Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
             Err(e) => Err(e.into()),
         }
     }
 
-    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
-        if let Some(env) = &config.environment {
-            if env.is_empty() {
-                return Err(ConfigError::ValidationError {
-                    field: "environment".to_string(),
-                });
+    /// Attributes every field set in `config` to this file.
+    fn provenance(&self, config: &AppConfig, contents: &str) -> Provenance {
+        let format = self.format();
+        Provenance::from_config(config, |field| ValueSource {
+            provider: self.name(),
+            path: Some(self.path.clone()),
+            // Line numbers are only tracked for TOML documents.
+            line: match format {
+                ConfigFormat::Toml => key_line(contents, field),
+                _ => None,
+            },
+        })
+    }
+
+    /// Returns a provider for an included file, which is read the same way
+    /// as this one but with its own format.
+    fn included(&self, path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            format: None,
+            validator: Validator::default(),
+            secret_key: self.secret_key.clone(),
+            strict_schema: self.strict_schema.clone(),
+            migrations: self.migrations.clone(),
+        }
+    }
+
+    /// Returns the files matched by `include` patterns, in order.
+    fn resolve_includes(&self, patterns: &[String]) -> Result<Vec<PathBuf>, ConfigError> {
+        let mut paths = Vec::new();
+        for pattern in patterns {
+            paths.extend(self.resolve_include(pattern)?);
+        }
+        Ok(paths)
+    }
+
+    /// Returns the files matched by one `include` pattern, sorted.
+    ///
+    /// A glob pattern may match nothing, but a plain path must name an
+    /// existing file.
+    fn resolve_include(&self, pattern: &str) -> Result<Vec<PathBuf>, ConfigError> {
+        let invalid = |reason: String| ConfigError::InvalidInclude {
+            pattern: pattern.to_string(),
+            reason,
+        };
+        let base = self.path.parent().unwrap_or(Path::new(""));
+        let base = glob::Pattern::escape(&base.to_string_lossy());
+        let full = Path::new(&base).join(pattern);
+        let mut matches = glob::glob(&full.to_string_lossy())
+            .map_err(|e| invalid(e.to_string()))?
+            .filter(|entry| entry.as_ref().map_or(true, |path| path.is_file()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| ConfigError::Io(e.into()))?;
+        if matches.is_empty() && !pattern.contains(['*', '?', '[']) {
+            return Err(invalid("no such file".to_string()));
+        }
+        matches.sort();
+        Ok(matches)
+    }
+
+    /// Loads the file and every file it includes, returning `Ok(None)` if
+    /// it does not exist.
+    ///
+    /// `chain` holds the files currently being loaded, outermost first, so
+    /// that include cycles are detected. Errors are reported with the path
+    /// of the file they were found in.
+    fn load_layers(
+        &self,
+        chain: &mut Vec<PathBuf>,
+        with_provenance: bool,
+    ) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        let in_file = |e: ConfigError| e.in_file(&self.path);
+        let Some(contents) = self.read().map_err(in_file)? else {
+            return Ok(None);
+        };
+        let canonical = fs::canonicalize(&self.path).map_err(|e| in_file(e.into()))?;
+        if let Some(start) = chain.iter().position(|path| *path == canonical) {
+            let mut cycle = chain[start..].to_vec();
+            cycle.push(canonical);
+            return Err(ConfigError::IncludeCycle(cycle));
+        }
+
+        let mut config = self.parse(&contents).map_err(in_file)?;
+        let mut provenance = if with_provenance {
+            self.provenance(&config, &contents)
+        } else {
+            Provenance::new()
+        };
+        let includes = std::mem::take(&mut config.include);
+        if includes.is_empty() {
+            return Ok(Some((config, provenance)));
+        }
+
+        chain.push(canonical);
+        for path in self.resolve_includes(&includes).map_err(in_file)? {
+            let layer = self.included(&path).load_layers(chain, with_provenance)?;
+            if let Some((layer, layer_provenance)) = layer {
+                merge_config(&mut config, layer);
+                provenance.merge(layer_provenance);
             }
         }
-        Ok(())
+        chain.pop();
+        Ok(Some((config, provenance)))
+    }
+
+    /// Adds the files this one includes, directly or indirectly, to
+    /// `paths`. Files that cannot be read are skipped.
+    fn collect_includes(&self, paths: &mut Vec<PathBuf>, seen: &mut Vec<PathBuf>) {
+        #[derive(Deserialize)]
+        struct Includes {
+            #[serde(default)]
+            include: Vec<String>,
+        }
+
+        let Ok(Some(contents)) = self.read() else {
+            return;
+        };
+        let Ok(Includes { include }) = self.format().parse(&contents) else {
+            return;
+        };
+        let resolved = include
+            .iter()
+            .flat_map(|pattern| self.resolve_include(pattern).unwrap_or_default());
+        for path in resolved {
+            let Ok(canonical) = fs::canonicalize(&path) else {
+                continue;
+            };
+            if !seen.contains(&canonical) {
+                seen.push(canonical);
+                paths.push(path.clone());
+                self.included(&path).collect_includes(paths, seen);
+            }
+        }
+    }
+}
+
+/// Finds the key and item for a dotted `field` path in a TOML document.
+///
+/// Each segment of the path descends into a table, so
+/// `monitoring.http.port` finds `port` in `[monitoring.http]` as well as the
+/// dotted form `monitoring.http.port = 80`. A key that itself contains dots,
+/// such as `"a.b"` in `[feature_flags]`, is matched before descending.
+fn find_key<'a>(
+    table: &'a dyn toml_edit::TableLike,
+    field: &str,
+) -> Option<(&'a toml_edit::Key, &'a toml_edit::Item)> {
+    if let Some(found) = table.get_key_value(field) {
+        return Some(found);
+    }
+    let (head, rest) = field.split_once('.')?;
+    find_key(table.get(head)?.as_table_like()?, rest)
+}
+
+/// The error for an `include` set by a source that is not a file, such as
+/// an environment variable, which has nothing to resolve it against.
+pub(crate) fn include_not_supported(pattern: &str, source: &str) -> ConfigError {
+    ConfigError::InvalidInclude {
+        pattern: pattern.to_string(),
+        reason: format!(
+            "`include` is only supported in configuration files, not in {}",
+            source
+        ),
+    }
+}
+
+/// Returns the 1-based line on which the key for a dotted `field` path is
+/// defined in a TOML document.
+pub(crate) fn key_line(contents: &str, field: &str) -> Option<usize> {
+    let offset = key_span(contents, field)?.start;
+    Some(contents[..offset].matches('\n').count() + 1)
+}
+
+/// Returns the byte range of the key for a dotted `field` path in a TOML
+/// document.
+pub(crate) fn key_span(contents: &str, field: &str) -> Option<Range<usize>> {
+    let document = toml_edit::ImDocument::parse(contents).ok()?;
+    let (key, _) = find_key(document.as_table(), field)?;
+    key.span()
+}
+
+/// Returns the byte range of the value of a dotted `field` path in a TOML
+/// document, e.g. `"verbose"` in `log_level = "verbose"`.
+pub(crate) fn value_span(contents: &str, field: &str) -> Option<Range<usize>> {
+    let document = toml_edit::ImDocument::parse(contents).ok()?;
+    let (_, item) = find_key(document.as_table(), field)?;
+    item.span()
+}
+
+impl ConfigurationProvider for FileConfigurationProvider {
+    fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        Ok(self
+            .load_layers(&mut Vec::new(), false)?
+            .map(|(config, _)| config))
+    }
+
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        self.load_layers(&mut Vec::new(), true)
+    }
+
+    fn name(&self) -> String {
+        "file".to_string()
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        self.validator.validate(config)
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        let mut paths = vec![self.path.clone()];
+        let mut seen = fs::canonicalize(&self.path).into_iter().collect();
+        self.collect_includes(&mut paths, &mut seen);
+        paths
     }
 }
 
@@ -79,7 +420,7 @@ mod tests {
         assert_eq!(config.environment, Some("production".to_string()));
         assert_eq!(config.log_level, Some(LogLevel::Debug));
         assert_eq!(config.log_format, Some(LogFormat::Json));
-        assert_eq!(config.feature_flags.get("new_ui"), Some(&true));
+        assert!(config.feature_flags["new_ui"].is_enabled());
     }
 
     #[test]
@@ -97,7 +438,11 @@ mod tests {
 
         let provider = FileConfigurationProvider::new(file.path());
         let result = provider.load();
-        assert!(matches!(result, Err(ConfigError::Toml(_))));
+        assert!(matches!(
+            result,
+            Err(ConfigError::File { path, source })
+                if path == file.path() && matches!(*source, ConfigError::Toml(_))
+        ));
     }
 
     #[test]
@@ -110,10 +455,7 @@ mod tests {
         let provider = FileConfigurationProvider::new(file.path());
         let config = provider.load().unwrap().unwrap();
         let result = provider.validate(&config);
-        assert!(matches!(
-            result,
-            Err(ConfigError::ValidationError { .. })
-        ));
+        assert!(matches!(result, Err(ConfigError::Validation(_))));
     }
 
     #[test]
@@ -130,4 +472,251 @@ mod tests {
         assert_eq!(config.environment, Some("development".to_string()));
         assert_eq!(config.log_level, Some(LogLevel::Info));
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_load_with_provenance_reports_lines() {
+        let file = create_temp_config_file(
+            "# Production overrides\nenvironment = \"production\"\nlog_level = \"warn\"\n\n[feature_flags]\nnew_ui = true\n",
+        );
+
+        let provider = FileConfigurationProvider::new(file.path());
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+
+        assert_eq!(config.environment, Some("production".to_string()));
+        let environment = provenance.get("environment").unwrap();
+        assert_eq!(environment.source.path.as_deref(), Some(file.path()));
+        assert_eq!(environment.source.line, Some(2));
+        assert_eq!(provenance.get("log_level").unwrap().source.line, Some(3));
+        assert_eq!(
+            provenance.get("feature_flags.new_ui").unwrap().source.line,
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn test_detects_format_from_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let json_path = dir.path().join("ciphr.json");
+        std::fs::write(&json_path, r#"{ "log_level": "warn", "feature_flags": { "beta": true } }"#)
+            .unwrap();
+        let yaml_path = dir.path().join("ciphr.yml");
+        std::fs::write(&yaml_path, "environment: staging\nlog_format: json\n").unwrap();
+
+        let config = FileConfigurationProvider::new(&json_path).load().unwrap().unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Warn));
+        assert!(config.feature_flags["beta"].is_enabled());
+
+        let config = FileConfigurationProvider::new(&yaml_path).load().unwrap().unwrap();
+        assert_eq!(config.environment, Some("staging".to_string()));
+        assert_eq!(config.log_format, Some(LogFormat::Json));
+    }
+
+    #[test]
+    fn test_explicit_format_overrides_extension() {
+        let file = create_temp_config_file(r#"{ "log_level": "error" }"#);
+
+        let provider = FileConfigurationProvider::new(file.path());
+        assert_eq!(provider.format(), ConfigFormat::Toml);
+        assert!(matches!(
+            provider.load(),
+            Err(ConfigError::File { source, .. }) if matches!(*source, ConfigError::Toml(_))
+        ));
+
+        let provider = FileConfigurationProvider::new(file.path()).with_format(ConfigFormat::Json);
+        let config = provider.load().unwrap().unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Error));
+    }
+
+    #[test]
+    fn test_load_invalid_json() {
+        let file = create_temp_config_file("{ \"log_level\": ");
+        let provider = FileConfigurationProvider::new(file.path()).with_format(ConfigFormat::Json);
+        assert!(matches!(
+            provider.load(),
+            Err(ConfigError::File { source, .. }) if matches!(*source, ConfigError::Json(_))
+        ));
+    }
+
+    #[test]
+    fn test_key_line_dotted_keys() {
+        let contents = "log_format = \"json\"\nfeature_flags.beta = true\n";
+        assert_eq!(key_line(contents, "log_format"), Some(1));
+        assert_eq!(key_line(contents, "feature_flags.beta"), Some(2));
+        assert_eq!(key_line(contents, "log_level"), None);
+
+        let contents = "[monitoring.http]\nhost = \"localhost\"\nport = 80\n";
+        assert_eq!(key_line(contents, "monitoring.http.port"), Some(3));
+    }
+
+    #[test]
+    fn test_load_decrypts_secrets() {
+        let key = SecretKey::generate();
+        let file = create_temp_config_file(&format!(
+            "[secrets]\ndb_password = \"{}\"\napi_token = \"plain\"\n",
+            key.encrypt("pa55")
+        ));
+
+        let provider = FileConfigurationProvider::new(file.path()).with_secret_key(key);
+        let config = provider.load().unwrap().unwrap();
+        assert_eq!(config.secrets["db_password"].expose(), "pa55");
+        assert_eq!(config.secrets["api_token"].expose(), "plain");
+
+        let provider =
+            FileConfigurationProvider::new(file.path()).with_secret_key(SecretKey::generate());
+        match provider.load() {
+            Err(ConfigError::File { source, .. }) => assert!(matches!(
+                *source,
+                ConfigError::Decryption { field, .. } if field == "secrets.db_password"
+            )),
+            other => panic!("expected a decryption error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_strict_mode_reports_unknown_keys() {
+        let file = create_temp_config_file(
+            "environment = \"development\"\nlog_levl = \"debug\"\n\n[logging]\nfile = \"x\"\n",
+        );
+
+        assert!(FileConfigurationProvider::new(file.path()).load().is_ok());
+
+        let result = FileConfigurationProvider::new(file.path()).strict().load();
+        let Err(ConfigError::UnknownKeys(unknown)) = result else {
+            panic!("expected unknown keys, got {:?}", result);
+        };
+        assert_eq!(
+            unknown.to_string(),
+            format!(
+                "{0}:2: unknown key `log_levl`, did you mean `log_level`?; {0}:4: unknown key `logging`",
+                file.path().display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_load_migrates_old_files() {
+        use crate::migration::Migration;
+
+        let migrations = MigrationRegistry::new()
+            .with_migration(Migration::new(1, "rename `log`").rename("log", "log_level"));
+        let file = create_temp_config_file("log = \"debug\"\n");
+
+        let config = FileConfigurationProvider::new(file.path())
+            .with_migrations(migrations.clone())
+            .strict()
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert_eq!(config.config_version, Some(2));
+
+        let invalid = create_temp_config_file("log = \"verbose\"\n");
+        let result = FileConfigurationProvider::new(invalid.path())
+            .with_migrations(migrations.clone())
+            .load();
+        assert!(matches!(
+            result,
+            Err(ConfigError::File { source, .. }) if matches!(*source, ConfigError::Toml(_))
+        ));
+
+        let newer = create_temp_config_file("config_version = 3\n");
+        let result = FileConfigurationProvider::new(newer.path())
+            .with_migrations(migrations)
+            .load();
+        assert!(matches!(
+            result,
+            Err(ConfigError::File { source, .. })
+                if matches!(*source, ConfigError::Migration { version: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_includes_in_lexical_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("ciphr.toml");
+        fs::write(
+            &main,
+            "include = [\"conf.d/*.toml\"]\nenvironment = \"production\"\nlog_level = \"info\"\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("conf.d")).unwrap();
+        fs::write(
+            dir.path().join("conf.d/20-debug.toml"),
+            "log_level = \"debug\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("conf.d/10-flags.toml"),
+            "log_level = \"warn\"\n[feature_flags]\nnew_ui = true\n",
+        )
+        .unwrap();
+
+        let provider = FileConfigurationProvider::new(&main).strict();
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+        assert_eq!(config.environment, Some("production".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+        assert!(config.feature_flags["new_ui"].is_enabled());
+        assert!(config.include.is_empty());
+
+        let log_level = provenance.get("log_level").unwrap();
+        assert_eq!(
+            log_level.source.path,
+            Some(dir.path().join("conf.d/20-debug.toml"))
+        );
+        assert_eq!(log_level.overridden.len(), 2);
+        assert_eq!(provider.watch_paths().len(), 3);
+    }
+
+    #[test]
+    fn test_include_errors_name_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("ciphr.toml");
+        fs::write(&main, "include = [\"local.toml\"]\n").unwrap();
+        fs::write(dir.path().join("local.toml"), "log_level = \"loud\"\n").unwrap();
+
+        let error = FileConfigurationProvider::new(&main).load().unwrap_err();
+        match &error {
+            ConfigError::File { path, source } => {
+                assert_eq!(path, &dir.path().join("local.toml"));
+                assert!(matches!(**source, ConfigError::Toml(_)));
+            }
+            other => panic!("expected a file error, got {:?}", other),
+        }
+        assert!(error.to_string().contains("local.toml"), "{}", error);
+
+        fs::write(&main, "include = [\"missing.toml\", \"conf.d/*.toml\"]\n").unwrap();
+        let result = FileConfigurationProvider::new(&main).load();
+        assert!(matches!(
+            result,
+            Err(ConfigError::File { source, .. })
+                if matches!(&*source, ConfigError::InvalidInclude { pattern, .. } if pattern == "missing.toml")
+        ));
+
+        fs::write(&main, "include = [\"[\"]\n").unwrap();
+        let result = FileConfigurationProvider::new(&main).load();
+        assert!(matches!(
+            result,
+            Err(ConfigError::File { source, .. })
+                if matches!(*source, ConfigError::InvalidInclude { .. })
+        ));
+    }
+
+    #[test]
+    fn test_include_cycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let main = dir.path().join("ciphr.toml");
+        fs::write(&main, "include = [\"a.toml\"]\n").unwrap();
+        fs::write(dir.path().join("a.toml"), "include = [\"b.toml\"]\n").unwrap();
+        fs::write(dir.path().join("b.toml"), "include = [\"a.toml\"]\n").unwrap();
+
+        let provider = FileConfigurationProvider::new(&main);
+        match provider.load() {
+            Err(ConfigError::IncludeCycle(cycle)) => {
+                let names: Vec<_> = cycle.iter().map(|path| path.file_name().unwrap()).collect();
+                assert_eq!(names, ["a.toml", "b.toml", "a.toml"]);
+            }
+            other => panic!("expected an include cycle, got {:?}", other),
+        }
+        assert_eq!(provider.watch_paths().len(), 3);
+    }
+}
@@ -0,0 +1,488 @@
+//! Versioned configuration files.
+//!
+//! Every file may declare the layout it was written for with a top-level
+//! `config_version`; files without one are version 1. When a key is renamed
+//! or moved, a `Migration` describing the change is registered, and files
+//! with an older version are upgraded when they are loaded, so existing
+//! deployments keep working. `ciphr config migrate` rewrites the files
+//! themselves.
+
+use crate::errors::ConfigError;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use toml_edit::{Decor, DocumentMut, Item, Table, TableLike};
+
+/// The name of the field that holds the version of a file.
+pub const VERSION_FIELD: &str = "config_version";
+
+/// The version assumed for files that do not declare one.
+pub const INITIAL_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Rename { from: String, to: String },
+    Remove(String),
+}
+
+/// The changes that upgrade a file from one version to the next.
+///
+/// Keys are given as dotted paths, so a flag can be moved into a section
+/// with `rename("feature_flags.metrics", "monitoring.enabled")`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migration {
+    from: u32,
+    description: String,
+    steps: Vec<Step>,
+}
+
+impl Migration {
+    /// Creates an empty migration from version `from` to `from + 1`.
+    pub fn new(from: u32, description: impl Into<String>) -> Self {
+        Self {
+            from,
+            description: description.into(),
+            steps: Vec::new(),
+        }
+    }
+
+    /// Moves the value at `from` to `to`, creating tables as needed.
+    ///
+    /// If the file already sets `to`, that value is kept and `from` is
+    /// dropped.
+    pub fn rename(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.steps.push(Step::Rename {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Drops the value at `path`.
+    pub fn remove(mut self, path: impl Into<String>) -> Self {
+        self.steps.push(Step::Remove(path.into()));
+        self
+    }
+
+    /// Returns the version this migration upgrades from.
+    pub fn from_version(&self) -> u32 {
+        self.from
+    }
+
+    /// Returns the description given to `new`.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn apply(&self, document: &mut impl Document) -> Result<(), ConfigError> {
+        let error = |reason: String| ConfigError::Migration {
+            version: self.from,
+            reason,
+        };
+        for step in &self.steps {
+            match step {
+                Step::Rename { from, to } => {
+                    if let Some(item) = document.take(from) {
+                        if !document.contains(to) {
+                            document.insert(to, item).map_err(error)?;
+                        }
+                    }
+                }
+                Step::Remove(path) => {
+                    document.take(path);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The outcome of upgrading a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Migrated {
+    /// The version the file declared.
+    pub from: u32,
+    /// The version it was upgraded to.
+    pub to: u32,
+    /// The description of each migration that was applied, in order.
+    pub applied: Vec<String>,
+}
+
+/// The migrations known to this build, keyed by the version they upgrade
+/// from.
+///
+/// `MigrationRegistry::default()` holds the migrations for the built-in
+/// fields. It is empty for now, since their layout has not changed since
+/// `config_version` was introduced, so files load as they are. Versions are
+/// shared by the whole file: crates that rename keys in their own sections
+/// add their migrations to the registry the application passes to its
+/// providers, with `with_migration`.
+#[derive(Debug, Clone, Default)]
+pub struct MigrationRegistry {
+    migrations: BTreeMap<u32, Migration>,
+}
+
+impl MigrationRegistry {
+    /// Creates a registry without any migrations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `migration`, replacing any other migration from the same
+    /// version.
+    ///
+    /// The registered migrations must form a chain from `INITIAL_VERSION`
+    /// to the latest version, one version at a time; `migrate` fails if a
+    /// version is skipped.
+    pub fn with_migration(mut self, migration: Migration) -> Self {
+        self.migrations.insert(migration.from, migration);
+        self
+    }
+
+    /// Returns the version files are upgraded to.
+    pub fn latest_version(&self) -> u32 {
+        self.migrations
+            .keys()
+            .next_back()
+            .map_or(INITIAL_VERSION, |from| from + 1)
+    }
+
+    /// Upgrades a parsed document to the latest version, returning `None`
+    /// if it is already up to date.
+    ///
+    /// A `config_version` that is not a number is left for the parser to
+    /// report. A version newer than `latest_version` fails with
+    /// `ConfigError::Migration`, since the file may use keys this build
+    /// does not understand, and so does a registry with a gap in its
+    /// chain of migrations.
+    pub fn migrate(&self, document: &mut Value) -> Result<Option<Migrated>, ConfigError> {
+        self.run(document)
+    }
+
+    /// Like `migrate`, but edits a TOML document in place, keeping its
+    /// comments and formatting.
+    pub fn migrate_toml(
+        &self,
+        document: &mut DocumentMut,
+    ) -> Result<Option<Migrated>, ConfigError> {
+        self.run(document)
+    }
+
+    /// Checks that there is a migration from every version between
+    /// `INITIAL_VERSION` and the latest one.
+    fn check_chain(&self) -> Result<(), ConfigError> {
+        for (expected, &from) in (INITIAL_VERSION..).zip(self.migrations.keys()) {
+            if from < INITIAL_VERSION {
+                return Err(ConfigError::Migration {
+                    version: from,
+                    reason: format!("versions start at {}", INITIAL_VERSION),
+                });
+            }
+            if from != expected {
+                return Err(ConfigError::Migration {
+                    version: expected,
+                    reason: format!(
+                        "no migration to version {} is registered, but there is one from version {}",
+                        expected + 1,
+                        from
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn run(&self, document: &mut impl Document) -> Result<Option<Migrated>, ConfigError> {
+        self.check_chain()?;
+        let Ok(version) = document.version() else {
+            return Ok(None);
+        };
+        let from = version.unwrap_or(INITIAL_VERSION);
+        let to = self.latest_version();
+        if from > to {
+            return Err(ConfigError::Migration {
+                version: from,
+                reason: format!("the latest supported version is {}", to),
+            });
+        }
+        if from == to {
+            return Ok(None);
+        }
+
+        let mut applied = Vec::new();
+        for migration in self.migrations.range(from..to).map(|(_, m)| m) {
+            migration.apply(document)?;
+            applied.push(migration.description.clone());
+        }
+        document.set_version(to);
+        Ok(Some(Migrated { from, to, applied }))
+    }
+}
+
+/// The operations a migration needs, implemented for both parsed documents
+/// and TOML documents that are edited in place.
+trait Document {
+    type Item;
+
+    /// Returns the declared version, or `Err` if it is not a number.
+    fn version(&self) -> Result<Option<u32>, ()>;
+    fn set_version(&mut self, version: u32);
+    fn contains(&self, path: &str) -> bool;
+    fn take(&mut self, path: &str) -> Option<Self::Item>;
+    fn insert(&mut self, path: &str, item: Self::Item) -> Result<(), String>;
+}
+
+fn split(path: &str) -> (Vec<&str>, &str) {
+    let mut segments: Vec<&str> = path.split('.').collect();
+    let key = segments.pop().expect("split returns at least one segment");
+    (segments, key)
+}
+
+fn not_a_table(path: &str, segment: &str) -> String {
+    format!(
+        "cannot move a value to `{}`: `{}` is not a table",
+        path, segment
+    )
+}
+
+impl Document for Value {
+    type Item = Value;
+
+    fn version(&self) -> Result<Option<u32>, ()> {
+        match self.get(VERSION_FIELD) {
+            None => Ok(None),
+            Some(version) => version
+                .as_u64()
+                .and_then(|version| u32::try_from(version).ok())
+                .map(Some)
+                .ok_or(()),
+        }
+    }
+
+    fn set_version(&mut self, version: u32) {
+        if let Value::Object(table) = self {
+            table.insert(VERSION_FIELD.to_string(), Value::from(version));
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.pointer(&format!("/{}", path.replace('.', "/")))
+            .is_some()
+    }
+
+    fn take(&mut self, path: &str) -> Option<Value> {
+        let (parents, key) = split(path);
+        let mut table = self.as_object_mut()?;
+        for segment in parents {
+            table = table.get_mut(segment)?.as_object_mut()?;
+        }
+        table.remove(key)
+    }
+
+    fn insert(&mut self, path: &str, item: Value) -> Result<(), String> {
+        let (parents, key) = split(path);
+        let mut table = self.as_object_mut().ok_or_else(|| not_a_table(path, ""))?;
+        for segment in parents {
+            table = table
+                .entry(segment)
+                .or_insert_with(|| Value::Object(Map::new()))
+                .as_object_mut()
+                .ok_or_else(|| not_a_table(path, segment))?;
+        }
+        table.insert(key.to_string(), item);
+        Ok(())
+    }
+}
+
+impl Document for DocumentMut {
+    /// The item together with the decor of its key, which holds the
+    /// comments above it.
+    type Item = (Decor, Item);
+
+    fn version(&self) -> Result<Option<u32>, ()> {
+        match self.get(VERSION_FIELD) {
+            None => Ok(None),
+            Some(version) => version
+                .as_integer()
+                .and_then(|version| u32::try_from(version).ok())
+                .map(Some)
+                .ok_or(()),
+        }
+    }
+
+    fn set_version(&mut self, version: u32) {
+        match self.get_mut(VERSION_FIELD).and_then(Item::as_value_mut) {
+            // Assigning through the existing value keeps its comments.
+            Some(existing) => {
+                let decor = existing.decor().clone();
+                *existing = i64::from(version).into();
+                *existing.decor_mut() = decor;
+            }
+            None => {
+                self.as_table_mut()
+                    .insert(VERSION_FIELD, toml_edit::value(i64::from(version)));
+            }
+        }
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        let (parents, key) = split(path);
+        let mut table = self.as_table() as &dyn TableLike;
+        for segment in parents {
+            match table.get(segment).and_then(Item::as_table_like) {
+                Some(next) => table = next,
+                None => return false,
+            }
+        }
+        table.contains_key(key)
+    }
+
+    fn take(&mut self, path: &str) -> Option<(Decor, Item)> {
+        let (parents, key) = split(path);
+        let mut table = self.as_table_mut() as &mut dyn TableLike;
+        for segment in parents {
+            table = table.get_mut(segment)?.as_table_like_mut()?;
+        }
+        let decor = table.get_key_value(key)?.0.leaf_decor().clone();
+        Some((decor, table.remove(key)?))
+    }
+
+    fn insert(&mut self, path: &str, (decor, item): (Decor, Item)) -> Result<(), String> {
+        let (parents, key) = split(path);
+        let mut table = self.as_table_mut() as &mut dyn TableLike;
+        for segment in parents {
+            table = table
+                .entry(segment)
+                .or_insert_with(|| {
+                    let mut table = Table::new();
+                    table.set_implicit(true);
+                    Item::Table(table)
+                })
+                .as_table_like_mut()
+                .ok_or_else(|| not_a_table(path, segment))?;
+        }
+        table.insert(key, item);
+        if let Some(mut key) = table.key_mut(key) {
+            *key.leaf_decor_mut() = decor;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::str::FromStr;
+
+    fn registry() -> MigrationRegistry {
+        MigrationRegistry::new()
+            .with_migration(
+                Migration::new(1, "rename `log` to `log_level`")
+                    .rename("log", "log_level")
+                    .remove("legacy_mode"),
+            )
+            .with_migration(
+                Migration::new(2, "move the metrics flag into [monitoring]")
+                    .rename("feature_flags.metrics", "monitoring.enabled"),
+            )
+    }
+
+    #[test]
+    fn test_migrates_parsed_document() {
+        let mut document = json!({
+            "log": "debug",
+            "legacy_mode": true,
+            "feature_flags": { "metrics": true, "new_ui": false },
+        });
+
+        let migrated = registry().migrate(&mut document).unwrap().unwrap();
+
+        assert_eq!(migrated.from, 1);
+        assert_eq!(migrated.to, 3);
+        assert_eq!(migrated.applied.len(), 2);
+        assert_eq!(
+            document,
+            json!({
+                "config_version": 3,
+                "log_level": "debug",
+                "feature_flags": { "new_ui": false },
+                "monitoring": { "enabled": true },
+            })
+        );
+    }
+
+    #[test]
+    fn test_only_applies_newer_migrations() {
+        let mut document = json!({ "config_version": 2, "log": "debug", "log_level": "warn" });
+        registry().migrate(&mut document).unwrap().unwrap();
+        assert_eq!(document["log"], "debug");
+
+        let mut current = json!({ "config_version": 3 });
+        assert_eq!(registry().migrate(&mut current).unwrap(), None);
+    }
+
+    #[test]
+    fn test_existing_destination_wins() {
+        let mut document = json!({ "log": "debug", "log_level": "warn" });
+        registry().migrate(&mut document).unwrap();
+        assert_eq!(
+            document,
+            json!({ "config_version": 3, "log_level": "warn" })
+        );
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let mut document = json!({ "config_version": 4 });
+        assert!(matches!(
+            registry().migrate(&mut document),
+            Err(ConfigError::Migration { version: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_gaps_in_the_chain() {
+        let registry = MigrationRegistry::new()
+            .with_migration(Migration::new(1, "rename `log`").rename("log", "log_level"))
+            .with_migration(Migration::new(3, "drop `legacy`").remove("legacy"));
+        let mut document = json!({ "log": "warn" });
+        assert!(matches!(
+            registry.migrate(&mut document),
+            Err(ConfigError::Migration { version: 2, .. })
+        ));
+        assert_eq!(document, json!({ "log": "warn" }));
+
+        let registry =
+            MigrationRegistry::new().with_migration(Migration::new(0, "before the first version"));
+        assert!(matches!(
+            registry.migrate(&mut json!({})),
+            Err(ConfigError::Migration { version: 0, .. })
+        ));
+    }
+
+    #[test]
+    fn test_migrates_toml_in_place() {
+        let mut document = DocumentMut::from_str(
+            "# Ciphr configuration\n\
+             log = \"debug\" # verbose while testing\n\
+             \n\
+             [feature_flags]\n\
+             metrics = true\n",
+        )
+        .unwrap();
+
+        registry().migrate_toml(&mut document).unwrap().unwrap();
+
+        assert_eq!(
+            document.to_string(),
+            "# Ciphr configuration\n\
+             log_level = \"debug\" # verbose while testing\n\
+             config_version = 3\n\
+             \n\
+             [feature_flags]\n\
+             \n\
+             [monitoring]\n\
+             enabled = true\n"
+        );
+    }
+}
@@ -0,0 +1,402 @@
+use crate::{
+    errors::ConfigError,
+    interpolate::Interpolator,
+    layers::merge_config,
+    loader::FileConfigurationProvider,
+    migration::MigrationRegistry,
+    provenance::{Provenance, ValueSource},
+    secret::SecretKey,
+    traits::ConfigurationProvider,
+    types::AppConfig,
+    validation::{ValidationErrors, ValidationFailure, Validator},
+};
+use std::path::{Path, PathBuf};
+
+/// The name of the required base file.
+pub const BASE_FILE_NAME: &str = "ciphr.toml";
+
+/// The name of the optional, machine-specific overlay applied last.
+pub const LOCAL_FILE_NAME: &str = "ciphr.local.toml";
+
+/// Returns the file name of the overlay for an environment, e.g.
+/// `ciphr.production.toml`.
+pub fn profile_file_name(environment: &str) -> String {
+    format!("ciphr.{}.toml", environment)
+}
+
+/// A configuration provider that resolves environment-specific overlays
+/// from a directory.
+///
+/// It loads, in order of increasing precedence:
+///
+/// 1. `ciphr.toml` (required),
+/// 2. `ciphr.{environment}.toml` (optional),
+/// 3. `ciphr.local.toml` (optional).
+///
+/// The environment comes from `with_environment` if set, otherwise from the
+/// `environment` field of the base file, with its `${...}` references
+/// resolved. An explicit environment is also written into the resulting
+/// configuration, so that it cannot disagree with the overlay that was
+/// chosen. Without one, an overlay that changes `environment` fails with
+/// `ConfigError::Validation`, since the overlay is chosen before it is read.
+///
+/// Like `LayeredConfigurationProvider`, references are resolved once all
+/// files are merged.
+pub struct ProfileConfigurationProvider {
+    base_dir: PathBuf,
+    environment: Option<String>,
+    validator: Validator,
+    interpolator: Interpolator,
+    secret_key: Option<SecretKey>,
+    migrations: MigrationRegistry,
+}
+
+impl ProfileConfigurationProvider {
+    /// Creates a provider that reads its files from `base_dir`.
+    pub fn new(base_dir: impl AsRef<Path>) -> Self {
+        Self {
+            base_dir: base_dir.as_ref().to_path_buf(),
+            environment: None,
+            validator: Validator::default(),
+            interpolator: Interpolator::new(),
+            secret_key: None,
+            migrations: MigrationRegistry::default(),
+        }
+    }
+
+    /// Selects the environment explicitly instead of reading it from the
+    /// base file.
+    pub fn with_environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Replaces the rules used to validate the merged configuration.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Replaces the interpolator run on the base file's `environment` and
+    /// on the merged configuration.
+    pub fn with_interpolator(mut self, interpolator: Interpolator) -> Self {
+        self.interpolator = interpolator;
+        self
+    }
+
+    /// Sets the key used to decrypt secrets, instead of reading it from the
+    /// environment.
+    pub fn with_secret_key(mut self, key: SecretKey) -> Self {
+        self.secret_key = Some(key);
+        self
+    }
+
+    /// Replaces the migrations applied to files with an older
+    /// `config_version`.
+    ///
+    /// Defaults to `MigrationRegistry::default()`.
+    pub fn with_migrations(mut self, migrations: MigrationRegistry) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    fn base_path(&self) -> PathBuf {
+        self.base_dir.join(BASE_FILE_NAME)
+    }
+
+    fn local_path(&self) -> PathBuf {
+        self.base_dir.join(LOCAL_FILE_NAME)
+    }
+
+    fn provider(&self, path: &Path) -> FileConfigurationProvider {
+        let provider =
+            FileConfigurationProvider::new(path).with_migrations(self.migrations.clone());
+        match &self.secret_key {
+            Some(key) => provider.with_secret_key(key.clone()),
+            None => provider,
+        }
+    }
+
+    /// Loads the base file.
+    ///
+    /// Fails with `ConfigError::NotFound` if it does not exist.
+    fn load_base(&self) -> Result<(AppConfig, Provenance), ConfigError> {
+        self.provider(&self.base_path())
+            .load_with_provenance()?
+            .ok_or_else(|| ConfigError::NotFound(self.base_path().display().to_string()))
+    }
+
+    /// Determines the active environment from the loaded base file.
+    fn resolve_environment(&self, base: &AppConfig) -> Result<Option<String>, ConfigError> {
+        let environment = match (&self.environment, &base.environment) {
+            (Some(environment), _) => Some(environment.clone()),
+            (None, Some(_)) => Some(self.interpolator.resolve(base, "environment")?),
+            (None, None) => None,
+        };
+
+        match environment {
+            // The name becomes part of a file name, so it must not be able
+            // to point outside the base directory.
+            Some(env) if env.is_empty() || env.contains(['/', '\\']) || env.starts_with('.') => {
+                Err(profile_error(format!(
+                    "`{}` cannot be used to select a profile overlay",
+                    env
+                )))
+            }
+            environment => Ok(environment),
+        }
+    }
+
+    /// Returns the files layered on top of the base file, in load order.
+    fn overlay_paths(&self, environment: Option<&str>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = environment
+            .map(|environment| self.base_dir.join(profile_file_name(environment)))
+            .into_iter()
+            .collect();
+        paths.push(self.local_path());
+        paths
+    }
+}
+
+fn profile_error(message: String) -> ConfigError {
+    ConfigError::Validation(ValidationErrors(vec![ValidationFailure::new(
+        "profile-environment",
+        "environment",
+        message,
+    )]))
+}
+
+impl ConfigurationProvider for ProfileConfigurationProvider {
+    fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        Ok(self.load_with_provenance()?.map(|(config, _)| config))
+    }
+
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        let (mut config, mut provenance) = self.load_base()?;
+        let environment = self.resolve_environment(&config)?;
+        for path in self.overlay_paths(environment.as_deref()) {
+            if let Some((overlay, overlay_provenance)) =
+                self.provider(&path).load_with_provenance()?
+            {
+                merge_config(&mut config, overlay);
+                provenance.merge(overlay_provenance);
+            }
+        }
+        self.interpolator.interpolate(&mut config)?;
+
+        if let Some(explicit) = &self.environment {
+            config.environment = Some(explicit.clone());
+            provenance.record("environment", ValueSource::named(self.name()));
+        } else if config.environment != environment {
+            let selected = match environment {
+                Some(environment) => format!("`{}`", profile_file_name(&environment)),
+                None => "no overlay".to_string(),
+            };
+            return Err(profile_error(format!(
+                "{} was selected from `{}`, but a later file sets the environment to `{}`",
+                selected,
+                BASE_FILE_NAME,
+                config.environment.unwrap_or_default()
+            )));
+        }
+        Ok(Some((config, provenance)))
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        self.validator.validate(config)
+    }
+
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        let environment = self
+            .load_base()
+            .and_then(|(base, _)| self.resolve_environment(&base));
+        let overlays = match environment {
+            Ok(environment) => self.overlay_paths(environment.as_deref()),
+            // Keep watching the base file so that fixing it triggers a reload.
+            Err(_) => vec![self.local_path()],
+        };
+
+        let mut paths = Vec::new();
+        for file in std::iter::once(self.base_path()).chain(overlays) {
+            for path in self.provider(&file).watch_paths() {
+                if !paths.contains(&path) {
+                    paths.push(path);
+                }
+            }
+        }
+        paths
+    }
+
+    fn name(&self) -> String {
+        "profile".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{LogFormat, LogLevel};
+    use std::fs;
+    use tempfile::{tempdir, TempDir};
+
+    fn profile_dir(files: &[(&str, &str)]) -> TempDir {
+        let dir = tempdir().unwrap();
+        for (name, content) in files {
+            fs::write(dir.path().join(name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_loads_overlays_in_order() {
+        let dir = profile_dir(&[
+            (
+                "ciphr.toml",
+                "environment = \"staging\"\nlog_level = \"info\"\nlog_format = \"text\"\n",
+            ),
+            (
+                "ciphr.staging.toml",
+                "log_level = \"warn\"\nlog_format = \"json\"\n",
+            ),
+            ("ciphr.production.toml", "log_level = \"error\"\n"),
+            ("ciphr.local.toml", "log_level = \"debug\"\n"),
+        ]);
+
+        let config = ProfileConfigurationProvider::new(dir.path())
+            .load()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(config.environment, Some("staging".to_string()));
+        assert_eq!(config.log_format, Some(LogFormat::Json));
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_explicit_environment() {
+        let dir = profile_dir(&[
+            (
+                "ciphr.toml",
+                "environment = \"development\"\nlog_level = \"info\"\n",
+            ),
+            ("ciphr.production.toml", "log_level = \"error\"\n"),
+        ]);
+
+        let provider = ProfileConfigurationProvider::new(dir.path()).with_environment("production");
+        let (config, provenance) = provider.load_with_provenance().unwrap().unwrap();
+
+        assert_eq!(config.environment, Some("production".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Error));
+        assert_eq!(
+            provenance.get("environment").unwrap().source,
+            ValueSource::named("profile")
+        );
+    }
+
+    #[test]
+    fn test_missing_overlays_are_optional() {
+        let dir = profile_dir(&[("ciphr.toml", "environment = \"test\"\n")]);
+
+        let config = ProfileConfigurationProvider::new(dir.path())
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.environment, Some("test".to_string()));
+    }
+
+    #[test]
+    fn test_missing_base_file() {
+        let dir = profile_dir(&[("ciphr.production.toml", "log_level = \"error\"\n")]);
+
+        let result = ProfileConfigurationProvider::new(dir.path())
+            .with_environment("production")
+            .load();
+        assert!(matches!(result, Err(ConfigError::NotFound(path)) if path.ends_with("ciphr.toml")));
+    }
+
+    #[test]
+    fn test_rejects_environment_outside_base_dir() {
+        let dir = profile_dir(&[("ciphr.toml", "environment = \"../secrets\"\n")]);
+
+        let result = ProfileConfigurationProvider::new(dir.path()).load();
+        assert!(matches!(result, Err(ConfigError::Validation(_))));
+    }
+
+    #[test]
+    fn test_interpolates_environment_before_selecting_overlay() {
+        let dir = profile_dir(&[
+            ("ciphr.toml", "environment = \"${env:PROBE_ENV}\"\n"),
+            ("ciphr.staging.toml", "log_level = \"warn\"\n"),
+        ]);
+
+        let config = ProfileConfigurationProvider::new(dir.path())
+            .with_interpolator(Interpolator::new().with_vars([("PROBE_ENV", "staging")]))
+            .load()
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(config.environment, Some("staging".to_string()));
+        assert_eq!(config.log_level, Some(LogLevel::Warn));
+    }
+
+    #[test]
+    fn test_rejects_overlay_changing_environment() {
+        let dir = profile_dir(&[
+            ("ciphr.toml", "environment = \"staging\"\n"),
+            ("ciphr.local.toml", "environment = \"production\"\n"),
+        ]);
+
+        match ProfileConfigurationProvider::new(dir.path()).load() {
+            Err(ConfigError::Validation(errors)) => assert_eq!(
+                errors.0[0].message,
+                "`ciphr.staging.toml` was selected from `ciphr.toml`, but a later file sets \
+                 the environment to `production`"
+            ),
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+
+        // An explicit environment wins over every file.
+        let config = ProfileConfigurationProvider::new(dir.path())
+            .with_environment("staging")
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.environment, Some("staging".to_string()));
+    }
+
+    #[test]
+    fn test_decrypts_overlays_with_secret_key() {
+        let key = SecretKey::generate();
+        let dir = profile_dir(&[
+            ("ciphr.toml", "environment = \"production\"\n"),
+            (
+                "ciphr.production.toml",
+                &format!("[secrets]\ndb_password = \"{}\"\n", key.encrypt("pa55")),
+            ),
+        ]);
+
+        let config = ProfileConfigurationProvider::new(dir.path())
+            .with_secret_key(key)
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.secrets["db_password"].expose(), "pa55");
+    }
+
+    #[test]
+    fn test_watch_paths_follow_environment() {
+        let dir = profile_dir(&[("ciphr.toml", "environment = \"staging\"\n")]);
+
+        let paths = ProfileConfigurationProvider::new(dir.path()).watch_paths();
+        assert_eq!(
+            paths,
+            vec![
+                dir.path().join("ciphr.toml"),
+                dir.path().join("ciphr.staging.toml"),
+                dir.path().join("ciphr.local.toml"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::types::AppConfig;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Describes where a configuration value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValueSource {
+    /// The name of the provider that supplied the value.
+    pub provider: String,
+    /// The file the value was read from, if the provider is file-based.
+    pub path: Option<PathBuf>,
+    /// The 1-based line of the key within `path`, where it can be determined.
+    pub line: Option<usize>,
+}
+
+impl ValueSource {
+    /// Creates a source that is identified only by its provider name.
+    pub fn named(provider: impl Into<String>) -> Self {
+        Self {
+            provider: provider.into(),
+            path: None,
+            line: None,
+        }
+    }
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.path, self.line) {
+            (Some(path), Some(line)) => write!(f, "{}:{}", path.display(), line),
+            (Some(path), None) => write!(f, "{}", path.display()),
+            (None, _) => write!(f, "{}", self.provider),
+        }
+    }
+}
+
+/// The provenance of a single field in a merged configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldProvenance {
+    /// The layer whose value ended up in the merged configuration.
+    pub source: ValueSource,
+    /// Earlier layers that also set the field, most recent first.
+    pub overridden: Vec<ValueSource>,
+}
+
+impl fmt::Display for FieldProvenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "set by {}", self.source)?;
+        if !self.overridden.is_empty() {
+            let overridden: Vec<String> = self.overridden.iter().map(ToString::to_string).collect();
+            write!(f, ", overriding {}", overridden.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Maps each field of a configuration to the layer that set it.
+///
+/// Fields are identified by the dotted paths returned by
+/// `AppConfig::fields`, e.g. `log_level` or `feature_flags.new_ui`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Provenance {
+    fields: BTreeMap<String, FieldProvenance>,
+}
+
+impl Provenance {
+    /// Creates an empty provenance map.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attributes every field that is set in `config` to a source.
+    ///
+    /// `locate` is called once per field to describe where it came from.
+    pub fn from_config(config: &AppConfig, mut locate: impl FnMut(&str) -> ValueSource) -> Self {
+        let mut provenance = Self::new();
+        for (field, _) in config.fields() {
+            let source = locate(&field);
+            provenance.record(field, source);
+        }
+        provenance
+    }
+
+    /// Records that `field` was set by `source`.
+    ///
+    /// If the field was already set by an earlier layer, that layer is kept
+    /// in the list of overridden sources.
+    pub fn record(&mut self, field: impl Into<String>, source: ValueSource) {
+        let field = field.into();
+        let entry = match self.fields.remove(&field) {
+            Some(previous) => {
+                let mut overridden = vec![previous.source];
+                overridden.extend(previous.overridden);
+                FieldProvenance { source, overridden }
+            }
+            None => FieldProvenance {
+                source,
+                overridden: Vec::new(),
+            },
+        };
+        self.fields.insert(field, entry);
+    }
+
+    /// Layers another provenance map on top of this one.
+    pub fn merge(&mut self, other: Provenance) {
+        for (field, provenance) in other.fields {
+            for source in provenance.overridden.into_iter().rev() {
+                self.record(field.clone(), source);
+            }
+            self.record(field, provenance.source);
+        }
+    }
+
+    /// Returns the provenance of a field, if it was set by any layer.
+    pub fn get(&self, field: &str) -> Option<&FieldProvenance> {
+        self.fields.get(field)
+    }
+
+    /// Iterates over all fields in lexical order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &FieldProvenance)> {
+        self.fields.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_source(path: &str, line: usize) -> ValueSource {
+        ValueSource {
+            provider: "file".to_string(),
+            path: Some(PathBuf::from(path)),
+            line: Some(line),
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_overridden_sources() {
+        let mut provenance = Provenance::new();
+        provenance.record("log_level", file_source("defaults.toml", 1));
+        provenance.record("log_level", file_source("staging.toml", 4));
+        provenance.record("log_level", file_source("/etc/ciphr/prod.toml", 3));
+
+        let field = provenance.get("log_level").unwrap();
+        assert_eq!(field.source, file_source("/etc/ciphr/prod.toml", 3));
+        assert_eq!(
+            field.to_string(),
+            "set by /etc/ciphr/prod.toml:3, overriding staging.toml:4, defaults.toml:1"
+        );
+    }
+
+    #[test]
+    fn test_merge_preserves_order() {
+        let mut base = Provenance::new();
+        base.record("environment", file_source("defaults.toml", 1));
+
+        let mut upper = Provenance::new();
+        upper.record("environment", file_source("a.toml", 2));
+        upper.record("environment", ValueSource::named("environment variables"));
+        upper.record("log_format", file_source("a.toml", 3));
+        base.merge(upper);
+
+        let field = base.get("environment").unwrap();
+        assert_eq!(field.source, ValueSource::named("environment variables"));
+        assert_eq!(
+            field.overridden,
+            vec![file_source("a.toml", 2), file_source("defaults.toml", 1)]
+        );
+        assert_eq!(base.get("log_format").unwrap().overridden, vec![]);
+    }
+}
@@ -0,0 +1,462 @@
+//! Loads configuration from a central HTTP(S) endpoint.
+
+use crate::{
+    errors::ConfigError, format::ConfigFormat, loader::include_not_supported,
+    migration::MigrationRegistry, secret::decrypt_secrets, traits::AsyncConfigurationProvider,
+    types::AppConfig, validation::Validator,
+};
+use async_trait::async_trait;
+use reqwest::{
+    header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED},
+    Client, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+/// How long a request may take before it counts as failed.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The last good response, kept in memory and optionally on disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedResponse {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    body: String,
+}
+
+/// A configuration provider that fetches a file over HTTP.
+///
+/// Requests are conditional: once a response has been seen, its `ETag` and
+/// `Last-Modified` headers are sent back as `If-None-Match` and
+/// `If-Modified-Since`, and a `304 Not Modified` reuses the cached body.
+///
+/// With `with_cache`, the last response that parsed successfully is also
+/// written to disk. If the server cannot be reached or returns an error,
+/// that copy is used instead, so instances can still start while the
+/// configuration service is down. A `404 Not Found` is treated like a
+/// missing file and returns `Ok(None)`. The cache file is readable only by
+/// its owner, since it holds the response as sent, secrets included.
+///
+/// Responses written for an older `config_version` are upgraded like files.
+/// Strict mode is not supported: unknown keys are ignored.
+pub struct HttpConfigurationProvider {
+    url: String,
+    client: Client,
+    format: Option<ConfigFormat>,
+    cache_path: Option<PathBuf>,
+    validator: Validator,
+    migrations: MigrationRegistry,
+    cached: Mutex<Option<CachedResponse>>,
+}
+
+impl HttpConfigurationProvider {
+    /// Creates a provider that fetches `url`.
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            client: Client::builder()
+                .timeout(DEFAULT_TIMEOUT)
+                .build()
+                .expect("the default HTTP client configuration is valid"),
+            format: None,
+            cache_path: None,
+            validator: Validator::default(),
+            migrations: MigrationRegistry::default(),
+            cached: Mutex::new(None),
+        }
+    }
+
+    /// Uses a preconfigured client, e.g. one with authentication headers or
+    /// a different timeout.
+    pub fn with_client(mut self, client: Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Forces the response to be parsed as `format`.
+    ///
+    /// By default the format is detected from the extension of the URL path
+    /// and falls back to TOML.
+    pub fn with_format(mut self, format: ConfigFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Keeps the last good response in `path` for offline starts.
+    pub fn with_cache(mut self, path: impl AsRef<Path>) -> Self {
+        self.cache_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Replaces the rules used by `validate`.
+    ///
+    /// Defaults to `Validator::default()`.
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = validator;
+        self
+    }
+
+    /// Replaces the migrations applied to responses with an older
+    /// `config_version`.
+    ///
+    /// Defaults to `MigrationRegistry::default()`.
+    pub fn with_migrations(mut self, migrations: MigrationRegistry) -> Self {
+        self.migrations = migrations;
+        self
+    }
+
+    /// Returns the format the response will be parsed as.
+    pub fn format(&self) -> ConfigFormat {
+        let path = self.url.split(['?', '#']).next().unwrap_or_default();
+        self.format
+            .or_else(|| ConfigFormat::from_path(path))
+            .unwrap_or(ConfigFormat::Toml)
+    }
+
+    fn error(&self, reason: impl ToString) -> ConfigError {
+        ConfigError::Remote {
+            url: self.url.clone(),
+            reason: reason.to_string(),
+        }
+    }
+
+    fn parse(&self, body: &str) -> Result<AppConfig, ConfigError> {
+        let format = self.format();
+        let mut document: serde_json::Value = format.parse(body)?;
+        let mut config: AppConfig = match self.migrations.migrate(&mut document)? {
+            Some(_) => format.from_value(document)?,
+            // Parsing the text again keeps the line numbers in errors.
+            None => format.parse(body)?,
+        };
+        if let Some(pattern) = config.include.first() {
+            return Err(include_not_supported(pattern, "remote configuration"));
+        }
+        decrypt_secrets(&mut config, None)?;
+        Ok(config)
+    }
+
+    /// Returns the last good response, reading it from disk on first use.
+    async fn cached(&self) -> Option<CachedResponse> {
+        if let Some(cached) = self.cached.lock().unwrap().clone() {
+            return Some(cached);
+        }
+        let contents = tokio::fs::read_to_string(self.cache_path.as_ref()?).await;
+        let cached: CachedResponse = match contents {
+            Ok(contents) => serde_json::from_str(&contents).ok()?,
+            Err(_) => return None,
+        };
+        *self.cached.lock().unwrap() = Some(cached.clone());
+        Some(cached)
+    }
+
+    async fn store(&self, response: CachedResponse) -> Result<(), io::Error> {
+        if let Some(path) = &self.cache_path {
+            // A unique name keeps processes sharing the cache from writing
+            // to the same temporary file, and the same directory keeps the
+            // rename atomic.
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let temp =
+                path.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4().simple()));
+            let mut result = write_private(&temp, &serde_json::to_vec(&response)?).await;
+            if result.is_ok() {
+                result = tokio::fs::rename(&temp, path).await;
+            }
+            if result.is_err() {
+                let _ = tokio::fs::remove_file(&temp).await;
+            }
+            result?;
+        }
+        *self.cached.lock().unwrap() = Some(response);
+        Ok(())
+    }
+
+    /// Performs a conditional request, returning the body to use.
+    async fn fetch(&self, cached: Option<&CachedResponse>) -> Result<Option<String>, ConfigError> {
+        let mut request = self.client.get(&self.url);
+        if let Some(cached) = cached {
+            if let Some(etag) = &cached.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &cached.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = request.send().await.map_err(|e| self.error(e))?;
+        match response.status() {
+            StatusCode::NOT_MODIFIED => match cached {
+                Some(cached) => Ok(Some(cached.body.clone())),
+                None => Err(self.error("server returned 304 without a cached copy")),
+            },
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => {
+                let header = |name| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(ToString::to_string)
+                };
+                let (etag, last_modified) = (header(ETAG), header(LAST_MODIFIED));
+                let body = response.text().await.map_err(|e| self.error(e))?;
+
+                // Only cache responses that parse, so a broken deployment
+                // does not replace the last good copy.
+                self.parse(&body)?;
+                if let Err(e) = self
+                    .store(CachedResponse {
+                        etag,
+                        last_modified,
+                        body: body.clone(),
+                    })
+                    .await
+                {
+                    tracing::warn!(url = %self.url, error = %e, "failed to cache remote configuration");
+                }
+                Ok(Some(body))
+            }
+            status => Err(self.error(format!("server returned {}", status))),
+        }
+    }
+}
+
+/// Writes `contents` to a file that must not exist yet, readable only by its
+/// owner, and flushes it to disk.
+async fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut options = tokio::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).await?;
+    file.write_all(contents).await?;
+    file.sync_all().await
+}
+
+#[async_trait]
+impl AsyncConfigurationProvider for HttpConfigurationProvider {
+    async fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+        let cached = self.cached().await;
+        match self.fetch(cached.as_ref()).await {
+            Ok(Some(body)) => Ok(Some(self.parse(&body)?)),
+            Ok(None) => Ok(None),
+            Err(e @ ConfigError::Remote { .. }) => match cached {
+                Some(cached) => {
+                    tracing::warn!(
+                        url = %self.url,
+                        error = %e,
+                        "using cached configuration; the remote source is unavailable"
+                    );
+                    Ok(Some(self.parse(&cached.body)?))
+                }
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        self.validator.validate(config)
+    }
+
+    fn name(&self) -> String {
+        "http".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::LogLevel;
+    use wiremock::{
+        matchers::{header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    #[tokio::test]
+    async fn test_conditional_requests_reuse_cached_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/ciphr.toml"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/ciphr.toml"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"v1\"")
+                    .set_body_string("log_level = \"warn\"\n"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = HttpConfigurationProvider::new(format!("{}/ciphr.toml", server.uri()));
+        for _ in 0..2 {
+            let config = provider.load().await.unwrap().unwrap();
+            assert_eq!(config.log_level, Some(LogLevel::Warn));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_disk_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("remote.json");
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"{ "log_level": "debug" }"#))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let url = format!("{}/config.json", server.uri());
+        HttpConfigurationProvider::new(&url)
+            .with_cache(&cache)
+            .load()
+            .await
+            .unwrap();
+
+        // A fresh provider, as after a restart, while the server is down.
+        let config = HttpConfigurationProvider::new(&url)
+            .with_cache(&cache)
+            .load()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+
+        let result = HttpConfigurationProvider::new(&url).load().await;
+        assert!(matches!(result, Err(ConfigError::Remote { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_server_without_cache() {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let provider = HttpConfigurationProvider::new(format!("http://127.0.0.1:{}/", port));
+        assert!(matches!(
+            provider.load().await,
+            Err(ConfigError::Remote { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_invalid_response_keeps_last_good_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("remote.toml.cache");
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("log_level = \"info\"\n"))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("log_level = "))
+            .mount(&server)
+            .await;
+
+        let provider = HttpConfigurationProvider::new(server.uri()).with_cache(&cache);
+        provider.load().await.unwrap();
+        assert!(matches!(provider.load().await, Err(ConfigError::Toml(_))));
+
+        let cached: CachedResponse =
+            serde_json::from_str(&std::fs::read_to_string(&cache).unwrap()).unwrap();
+        assert_eq!(cached.body, "log_level = \"info\"\n");
+    }
+
+    #[tokio::test]
+    async fn test_cache_is_private_and_replaced_atomically() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("remote.json");
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("[secrets]\ndb_password = \"pa55\"\n"),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = HttpConfigurationProvider::new(server.uri()).with_cache(&cache);
+        provider.load().await.unwrap();
+        provider.load().await.unwrap();
+
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, ["remote.json"]);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&cache).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_migrates_old_responses() {
+        use crate::migration::Migration;
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("log = \"warn\"\n"))
+            .mount(&server)
+            .await;
+
+        let config = HttpConfigurationProvider::new(server.uri())
+            .with_migrations(MigrationRegistry::new().with_migration(
+                Migration::new(1, "rename `log` to `log_level`").rename("log", "log_level"),
+            ))
+            .load()
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Warn));
+    }
+
+    #[tokio::test]
+    async fn test_include_is_rejected() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_string("include = [\"local.toml\"]\n"),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = HttpConfigurationProvider::new(server.uri());
+        assert!(matches!(
+            provider.load().await,
+            Err(ConfigError::InvalidInclude { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_not_found_is_none() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let provider = HttpConfigurationProvider::new(server.uri());
+        assert!(provider.load().await.unwrap().is_none());
+    }
+}
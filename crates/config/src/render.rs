@@ -0,0 +1,117 @@
+//! Serializes a configuration back to a file format.
+//!
+//! Secrets are always written as `[REDACTED]`, so the output is safe to print
+//! or attach to a bug report. It is not meant to be loaded again as-is.
+
+use crate::{errors::ConfigError, format::ConfigFormat, provenance::Provenance, types::AppConfig};
+use std::str::FromStr;
+use toml_edit::{DocumentMut, Item, TableLike};
+
+/// Serializes `config` in the given format.
+///
+/// JSON5 output is plain JSON, which every JSON5 parser accepts.
+pub fn render(config: &AppConfig, format: ConfigFormat) -> Result<String, ConfigError> {
+    let error = |e: &dyn std::fmt::Display| ConfigError::Serialization(e.to_string());
+    match format {
+        ConfigFormat::Toml => toml::to_string(config).map_err(|e| error(&e)),
+        ConfigFormat::Json | ConfigFormat::Json5 => {
+            serde_json::to_string_pretty(config).map_err(|e| error(&e))
+        }
+        ConfigFormat::Yaml => serde_norway::to_string(config).map_err(|e| error(&e)),
+    }
+}
+
+/// Serializes `config` as TOML with a trailing comment on every value naming
+/// the layer that set it, e.g. `log_level = "debug" # /etc/ciphr/prod.toml:3`.
+pub fn render_with_sources(
+    config: &AppConfig,
+    provenance: &Provenance,
+) -> Result<String, ConfigError> {
+    let rendered = render(config, ConfigFormat::Toml)?;
+    let mut document =
+        DocumentMut::from_str(&rendered).map_err(|e| ConfigError::Serialization(e.to_string()))?;
+
+    for (field, source) in provenance.iter() {
+        if let Some(value) = find_mut(document.as_table_mut(), field).and_then(Item::as_value_mut) {
+            value
+                .decor_mut()
+                .set_suffix(format!(" # {}", source.source));
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// Finds the item for a dotted `field` path, matching keys that contain dots
+/// before descending into tables.
+pub(crate) fn find_mut<'a>(table: &'a mut dyn TableLike, field: &str) -> Option<&'a mut Item> {
+    if table.contains_key(field) {
+        return table.get_mut(field);
+    }
+    let (head, rest) = field.split_once('.')?;
+    find_mut(table.get_mut(head)?.as_table_like_mut()?, rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        builder::AppConfigBuilder,
+        provenance::ValueSource,
+        types::{LogFormat, LogLevel},
+    };
+    use std::path::PathBuf;
+
+    fn config() -> AppConfig {
+        AppConfigBuilder::new()
+            .environment("production")
+            .log_level(LogLevel::Warn)
+            .log_format(LogFormat::Json)
+            .feature_flag("new_ui", true)
+            .secret("db_password", "pa55")
+            .build()
+    }
+
+    #[test]
+    fn test_render_each_format_redacts_secrets() {
+        let config = config();
+
+        for format in [ConfigFormat::Toml, ConfigFormat::Json, ConfigFormat::Yaml] {
+            let rendered = render(&config, format).unwrap();
+            assert!(!rendered.contains("pa55"), "{} leaked a secret", format);
+
+            let mut parsed: AppConfig = format.parse(&rendered).unwrap();
+            assert_eq!(parsed.secrets["db_password"].expose(), "[REDACTED]");
+            parsed.secrets = config.secrets.clone();
+            assert_eq!(parsed, config, "{} did not round-trip", format);
+        }
+    }
+
+    #[test]
+    fn test_render_with_sources() {
+        let config = AppConfigBuilder::new()
+            .log_level(LogLevel::Debug)
+            .feature_flag("new_ui", true)
+            .build();
+        let mut provenance = Provenance::new();
+        provenance.record(
+            "log_level",
+            ValueSource {
+                provider: "file".to_string(),
+                path: Some(PathBuf::from("prod.toml")),
+                line: Some(3),
+            },
+        );
+        provenance.record(
+            "feature_flags.new_ui",
+            ValueSource::named("environment variable CIPHR_FEATURE_FLAGS__NEW_UI"),
+        );
+
+        let rendered = render_with_sources(&config, &provenance).unwrap();
+
+        assert!(rendered.contains("log_level = \"debug\" # prod.toml:3\n"));
+        assert!(
+            rendered.contains("new_ui = true # environment variable CIPHR_FEATURE_FLAGS__NEW_UI\n")
+        );
+        assert!(rendered.contains("environment = \"development\"\n"));
+    }
+}
@@ -0,0 +1,393 @@
+//! Targeting rules for feature flags.
+//!
+//! A rule is either a condition on one attribute of the evaluation context
+//! or a combination of rules with `all` (AND) and `any` (OR):
+//!
+//! ```toml
+//! [feature_flags.multi_currency]
+//! rule.all = [
+//!     { attribute = "plan", in = ["pro", "enterprise"] },
+//!     { attribute = "region", equals = "eu" },
+//! ]
+//! ```
+//!
+//! A condition takes exactly one operator:
+//!
+//! | Operator              | Matches an attribute that                          |
+//! |-----------------------|----------------------------------------------------|
+//! | `equals = "eu"`       | is exactly the value                               |
+//! | `in = ["a", "b"]`     | is one of the values                               |
+//! | `matches = "^acme-"`  | matches the regular expression                     |
+//! | `version = ">=2.1"`   | is a semantic version meeting the requirement      |
+//! | `min = 1`, `max = 10` | is a number in the inclusive range; either bound may be left out |
+//! | `after = "2025-01-01"`| is a date or RFC 3339 timestamp after the given one |
+//!
+//! This module only describes rules; the `feature-flags` crate evaluates
+//! them. `Rule::check` reports operands that can never be evaluated, such
+//! as an invalid regular expression; the `flag-rules` validation rule runs
+//! it on every flag.
+
+use chrono::{DateTime, NaiveDate};
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+/// A targeting rule.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "RuleTable", into = "RuleTable")]
+pub enum Rule {
+    /// Matches if every rule matches.
+    All(Vec<Rule>),
+    /// Matches if at least one rule matches.
+    Any(Vec<Rule>),
+    /// Matches if the condition holds.
+    Condition(Condition),
+}
+
+impl Rule {
+    /// Creates a rule that checks a single attribute.
+    pub fn condition(attribute: impl Into<String>, operator: Operator) -> Self {
+        Rule::Condition(Condition {
+            attribute: attribute.into(),
+            operator,
+        })
+    }
+}
+
+impl Rule {
+    /// Checks that every operand in the rule can be evaluated, returning
+    /// the first problem found.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Rule::All(rules) | Rule::Any(rules) => rules.iter().try_for_each(Rule::check),
+            Rule::Condition(condition) => condition.operator.check(),
+        }
+    }
+}
+
+/// A check on one attribute of the evaluation context.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition {
+    /// The attribute to check, e.g. `plan`.
+    pub attribute: String,
+    /// How the attribute is compared.
+    pub operator: Operator,
+}
+
+/// How a condition compares an attribute.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operator {
+    /// The attribute is exactly this value.
+    Equals(String),
+    /// The attribute is one of these values.
+    In(Vec<String>),
+    /// The attribute matches this regular expression.
+    Matches(String),
+    /// The attribute is a semantic version meeting this requirement, e.g.
+    /// `>=2.1, <3`.
+    Version(String),
+    /// The attribute is a number within these inclusive bounds.
+    Range { min: Option<f64>, max: Option<f64> },
+    /// The attribute is a date or timestamp after this one.
+    After(String),
+}
+
+impl Operator {
+    /// Checks that the operand can be evaluated: patterns must be regular
+    /// expressions, requirements semantic version requirements, dates
+    /// dates or RFC 3339 timestamps, and ranges finite and not inverted.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Operator::Equals(_) | Operator::In(_) => Ok(()),
+            Operator::Matches(pattern) => match regex::Regex::new(pattern) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(format!("invalid regular expression `{}`: {}", pattern, e)),
+            },
+            Operator::Version(requirement) => match semver::VersionReq::parse(requirement) {
+                Ok(_) => Ok(()),
+                Err(e) => Err(format!(
+                    "invalid version requirement `{}`: {}",
+                    requirement, e
+                )),
+            },
+            Operator::Range { min, max } => {
+                if let Some(bound) = min.iter().chain(max).find(|bound| !bound.is_finite()) {
+                    return Err(format!(
+                        "invalid range bound {}, expected a finite number",
+                        bound
+                    ));
+                }
+                match (min, max) {
+                    (Some(min), Some(max)) if min > max => Err(format!(
+                        "invalid range: `min` ({}) is greater than `max` ({})",
+                        min, max
+                    )),
+                    _ => Ok(()),
+                }
+            }
+            Operator::After(date) => {
+                let date = date.trim();
+                if DateTime::parse_from_rfc3339(date).is_ok()
+                    || NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
+                {
+                    Ok(())
+                } else {
+                    Err(format!(
+                        "invalid date `{}`, expected e.g. `2025-01-01` or `2025-01-01T09:00:00Z`",
+                        date
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/// The table form of a rule, as written in configuration files.
+#[derive(Default, Serialize, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct RuleTable {
+    /// Rules that must all match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    all: Option<Vec<Rule>>,
+    /// Rules of which at least one must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    any: Option<Vec<Rule>>,
+    /// The attribute a condition checks.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    attribute: Option<String>,
+    /// The value the attribute must equal.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    equals: Option<String>,
+    /// The values the attribute must be one of.
+    #[serde(default, rename = "in", skip_serializing_if = "Option::is_none")]
+    in_list: Option<Vec<String>>,
+    /// A regular expression the attribute must match.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    matches: Option<String>,
+    /// A semantic version requirement, e.g. `>=2.1`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    version: Option<String>,
+    /// The smallest number the attribute may be.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    min: Option<f64>,
+    /// The largest number the attribute may be.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    max: Option<f64>,
+    /// The date or timestamp the attribute must be after.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    after: Option<String>,
+}
+
+impl TryFrom<RuleTable> for Rule {
+    type Error = String;
+
+    fn try_from(table: RuleTable) -> Result<Self, Self::Error> {
+        let mut operators = Vec::new();
+        operators.extend(table.equals.map(Operator::Equals));
+        operators.extend(table.in_list.map(Operator::In));
+        operators.extend(table.matches.map(Operator::Matches));
+        operators.extend(table.version.map(Operator::Version));
+        if table.min.is_some() || table.max.is_some() {
+            operators.push(Operator::Range {
+                min: table.min,
+                max: table.max,
+            });
+        }
+        operators.extend(table.after.map(Operator::After));
+
+        match (table.all, table.any, table.attribute) {
+            (Some(rules), None, None) if operators.is_empty() => Ok(Rule::All(rules)),
+            (None, Some(rules), None) if operators.is_empty() => Ok(Rule::Any(rules)),
+            (None, None, Some(attribute)) => match operators.len() {
+                1 => Ok(Rule::condition(attribute, operators.remove(0))),
+                0 => Err(format!(
+                    "the condition on `{}` needs an operator: `equals`, `in`, `matches`, \
+                     `version`, `min`/`max` or `after`",
+                    attribute
+                )),
+                _ => Err(format!(
+                    "the condition on `{}` has more than one operator",
+                    attribute
+                )),
+            },
+            _ => Err(
+                "a rule needs exactly one of `all`, `any` or an `attribute` with an operator"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+impl From<Rule> for RuleTable {
+    fn from(rule: Rule) -> Self {
+        match rule {
+            Rule::All(rules) => Self {
+                all: Some(rules),
+                ..Default::default()
+            },
+            Rule::Any(rules) => Self {
+                any: Some(rules),
+                ..Default::default()
+            },
+            Rule::Condition(Condition {
+                attribute,
+                operator,
+            }) => {
+                let mut table = Self {
+                    attribute: Some(attribute),
+                    ..Default::default()
+                };
+                match operator {
+                    Operator::Equals(value) => table.equals = Some(value),
+                    Operator::In(values) => table.in_list = Some(values),
+                    Operator::Matches(pattern) => table.matches = Some(pattern),
+                    Operator::Version(requirement) => table.version = Some(requirement),
+                    Operator::Range { min, max } => {
+                        table.min = min;
+                        table.max = max;
+                    }
+                    Operator::After(date) => table.after = Some(date),
+                }
+                table
+            }
+        }
+    }
+}
+
+impl JsonSchema for Rule {
+    fn schema_name() -> Cow<'static, str> {
+        "Rule".into()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        RuleTable::json_schema(generator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct Flag {
+        rule: Rule,
+    }
+
+    #[test]
+    fn test_parses_nested_rules() {
+        let flag: Flag = toml::from_str(
+            r#"
+            rule.any = [
+                { attribute = "internal", equals = "true" },
+                { all = [
+                    { attribute = "plan", in = ["pro", "enterprise"] },
+                    { attribute = "seats", min = 10 },
+                ] },
+            ]
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            flag.rule,
+            Rule::Any(vec![
+                Rule::condition("internal", Operator::Equals("true".to_string())),
+                Rule::All(vec![
+                    Rule::condition(
+                        "plan",
+                        Operator::In(vec!["pro".to_string(), "enterprise".to_string()])
+                    ),
+                    Rule::condition(
+                        "seats",
+                        Operator::Range {
+                            min: Some(10.0),
+                            max: None
+                        }
+                    ),
+                ]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_rejects_malformed_conditions() {
+        for (rule, message) in [
+            (r#"{ attribute = "plan" }"#, "needs an operator"),
+            (
+                r#"{ attribute = "plan", equals = "pro", matches = "p" }"#,
+                "more than one operator",
+            ),
+            (
+                r#"{ all = [], attribute = "plan", equals = "pro" }"#,
+                "exactly one of",
+            ),
+            (r#"{ attribute = "plan", equal = "pro" }"#, "unknown field"),
+        ] {
+            let error = toml::from_str::<Flag>(&format!("rule = {}", rule)).unwrap_err();
+            assert!(error.to_string().contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_check_reports_invalid_operands() {
+        let valid = Rule::All(vec![
+            Rule::condition("email", Operator::Matches("@acme\\.com$".to_string())),
+            Rule::condition("app_version", Operator::Version(">=2.1, <3".to_string())),
+            Rule::condition(
+                "signup",
+                Operator::After("2025-01-01T09:00:00Z".to_string()),
+            ),
+        ]);
+        assert_eq!(valid.check(), Ok(()));
+
+        for (operator, message) in [
+            (
+                Operator::Matches("[a-z".to_string()),
+                "invalid regular expression",
+            ),
+            (
+                Operator::Version("~>2".to_string()),
+                "invalid version requirement",
+            ),
+            (Operator::After("01/02/2025".to_string()), "invalid date"),
+            (
+                Operator::Range {
+                    min: Some(5.0),
+                    max: Some(1.0),
+                },
+                "greater than `max`",
+            ),
+            (
+                Operator::Range {
+                    min: Some(f64::NAN),
+                    max: None,
+                },
+                "finite number",
+            ),
+        ] {
+            let rule = Rule::Any(vec![Rule::condition("attr", operator)]);
+            let error = rule.check().unwrap_err();
+            assert!(error.contains(message), "{}", error);
+        }
+    }
+
+    #[test]
+    fn test_round_trips_through_json() {
+        let rule = Rule::All(vec![
+            Rule::condition("region", Operator::Equals("eu".to_string())),
+            Rule::condition("app_version", Operator::Version(">=2.1".to_string())),
+            Rule::condition("signup", Operator::After("2025-01-01".to_string())),
+        ]);
+
+        let json = serde_json::to_value(&rule).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "all": [
+                { "attribute": "region", "equals": "eu" },
+                { "attribute": "app_version", "version": ">=2.1" },
+                { "attribute": "signup", "after": "2025-01-01" },
+            ] })
+        );
+        assert_eq!(serde_json::from_value::<Rule>(json).unwrap(), rule);
+    }
+}
@@ -0,0 +1,119 @@
+//! JSON Schema for configuration files.
+//!
+//! The schema is generated from the Rust types in `config::types`, so it
+//! always matches what the parser accepts. Editors that understand JSON
+//! Schema, such as Taplo for TOML or the YAML language server, can use it
+//! for completion and inline validation.
+
+use crate::{section::ConfigSection, types::AppConfig};
+use schemars::{generate::SchemaSettings, JsonSchema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+/// Builds the JSON Schema for `AppConfig`, optionally extended with the
+/// typed sections registered by other crates.
+pub struct SchemaBuilder {
+    generator: SchemaGenerator,
+    sections: Vec<(&'static str, Schema)>,
+}
+
+impl SchemaBuilder {
+    /// Creates a builder for the built-in fields only.
+    pub fn new() -> Self {
+        Self {
+            generator: SchemaSettings::draft2020_12()
+                .for_deserialize()
+                .into_generator(),
+            sections: Vec::new(),
+        }
+    }
+
+    /// Adds the `T::NAME` table, described by `T`, to the schema.
+    pub fn with_section<T: ConfigSection + JsonSchema>(mut self) -> Self {
+        let schema = self.generator.subschema_for::<T>();
+        self.sections.push((T::NAME, schema));
+        self
+    }
+
+    /// Returns the schema as a JSON document.
+    pub fn build(self) -> Value {
+        let mut schema = self.generator.into_root_schema_for::<AppConfig>();
+        let properties = schema
+            .ensure_object()
+            .entry("properties")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Value::Object(properties) = properties {
+            for (name, section) in self.sections {
+                properties.insert(name.to_string(), section.to_value());
+            }
+        }
+        schema.to_value()
+    }
+}
+
+impl Default for SchemaBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returns the JSON Schema for `AppConfig` without any custom sections.
+pub fn app_config_schema() -> Value {
+    SchemaBuilder::new().build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Monitoring {
+        /// Where metrics are pushed.
+        endpoint: String,
+    }
+
+    impl ConfigSection for Monitoring {
+        const NAME: &'static str = "monitoring";
+    }
+
+    #[test]
+    fn test_schema_describes_built_in_fields() {
+        let schema = app_config_schema();
+
+        assert_eq!(schema["title"], "AppConfig");
+        assert_eq!(
+            schema["$defs"]["LogLevel"]["enum"],
+            json!(["trace", "debug", "info", "warn", "error"])
+        );
+        assert_eq!(schema["$defs"]["LogFormat"]["enum"], json!(["text", "json"]));
+        assert_eq!(
+            schema["properties"]["feature_flags"]["additionalProperties"]["$ref"],
+            "#/$defs/FlagDefinition"
+        );
+        assert_eq!(
+            schema["$defs"]["FlagDefinition"]["anyOf"][1]["type"],
+            "boolean"
+        );
+        assert_eq!(
+            schema["$defs"]["FlagTable"]["properties"]["rollout"]["type"],
+            json!(["number", "null"])
+        );
+        assert_eq!(
+            schema["properties"]["secrets"]["additionalProperties"]["type"],
+            "string"
+        );
+    }
+
+    #[test]
+    fn test_schema_includes_registered_sections() {
+        let schema = SchemaBuilder::new().with_section::<Monitoring>().build();
+
+        let reference = schema["properties"]["monitoring"]["$ref"].as_str().unwrap();
+        assert_eq!(reference, "#/$defs/Monitoring");
+        assert_eq!(
+            schema["$defs"]["Monitoring"]["properties"]["endpoint"]["description"],
+            "Where metrics are pushed."
+        );
+    }
+}
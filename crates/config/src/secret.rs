@@ -0,0 +1,408 @@
+//! Encrypted secret values and a redacting `Secret` wrapper.
+//!
+//! Secrets are stored in configuration files as `enc:v1:<base64>`, where the
+//! payload is a random 96-bit nonce followed by the ChaCha20-Poly1305
+//! ciphertext of the UTF-8 plaintext. They are decrypted at load time with a
+//! 256-bit `SecretKey`, which is read from:
+//!
+//! 1. a key passed explicitly to the provider, or
+//! 2. the `CIPHR_SECRET_KEY` environment variable (base64), or
+//! 3. the file named by the `CIPHR_SECRET_KEY_FILE` environment variable.
+//!
+//! Use `ciphr config generate-key` and `ciphr config encrypt` to produce keys
+//! and ciphertexts.
+
+use crate::{errors::ConfigError, types::AppConfig};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::borrow::Cow;
+use std::{fmt, fs, path::Path};
+
+/// The prefix that marks an encrypted value.
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// The environment variable holding a base64-encoded key.
+pub const SECRET_KEY_ENV: &str = "CIPHR_SECRET_KEY";
+
+/// The environment variable holding the path of a key file.
+pub const SECRET_KEY_FILE_ENV: &str = "CIPHR_SECRET_KEY_FILE";
+
+const REDACTED: &str = "[REDACTED]";
+const NONCE_LEN: usize = 12;
+
+/// A value that must never appear in logs or serialized output.
+///
+/// `Debug`, `Display` and `Serialize` all print `[REDACTED]`. Use
+/// `expose` to read the actual value.
+#[derive(Clone, Default)]
+pub struct Secret<T> {
+    value: T,
+    /// Set for values decrypted from `enc:v1:`, which are used verbatim and
+    /// never interpolated.
+    decrypted: bool,
+}
+
+impl<T> Secret<T> {
+    /// Wraps a value.
+    pub fn new(value: T) -> Self {
+        Self {
+            value,
+            decrypted: false,
+        }
+    }
+
+    /// Wraps a value decrypted from an `enc:v1:` ciphertext.
+    pub(crate) fn decrypted(value: T) -> Self {
+        Self {
+            value,
+            decrypted: true,
+        }
+    }
+
+    /// Returns `true` if the value was decrypted from an `enc:v1:` ciphertext.
+    pub(crate) fn is_decrypted(&self) -> bool {
+        self.decrypted
+    }
+
+    /// Returns the wrapped value.
+    pub fn expose(&self) -> &T {
+        &self.value
+    }
+
+    /// Consumes the wrapper and returns the value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> From<T> for Secret<T> {
+    fn from(value: T) -> Self {
+        Self::new(value)
+    }
+}
+
+// Two secrets are equal if their values are, however they were loaded.
+impl<T: PartialEq> PartialEq for Secret<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<T: Eq> Eq for Secret<T> {}
+
+impl<T> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> fmt::Display for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", REDACTED)
+    }
+}
+
+impl<T> Serialize for Secret<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(REDACTED)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Secret<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Secret::new)
+    }
+}
+
+// Secrets are written in configuration files like the value they wrap.
+impl<T: JsonSchema> JsonSchema for Secret<T> {
+    fn inline_schema() -> bool {
+        true
+    }
+
+    fn schema_name() -> Cow<'static, str> {
+        T::schema_name()
+    }
+
+    fn json_schema(generator: &mut SchemaGenerator) -> Schema {
+        T::json_schema(generator)
+    }
+}
+
+/// Returns `true` if `value` is an encrypted secret.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// A 256-bit key used to encrypt and decrypt secrets.
+#[derive(Clone)]
+pub struct SecretKey(Key);
+
+impl SecretKey {
+    /// Generates a new random key.
+    pub fn generate() -> Self {
+        Self(ChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    /// Decodes a base64-encoded key.
+    pub fn from_base64(encoded: &str) -> Result<Self, ConfigError> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| ConfigError::InvalidSecretKey(format!("not valid base64: {}", e)))?;
+        if bytes.len() != 32 {
+            return Err(ConfigError::InvalidSecretKey(format!(
+                "expected 32 bytes, got {}",
+                bytes.len()
+            )));
+        }
+        Ok(Self(*Key::from_slice(&bytes)))
+    }
+
+    /// Reads a base64-encoded key from a file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        Self::from_base64(&fs::read_to_string(path)?)
+    }
+
+    /// Reads the key from `CIPHR_SECRET_KEY`, or from the file named by
+    /// `CIPHR_SECRET_KEY_FILE`.
+    ///
+    /// Returns `Ok(None)` if neither variable is set.
+    pub fn from_env() -> Result<Option<Self>, ConfigError> {
+        if let Ok(encoded) = std::env::var(SECRET_KEY_ENV) {
+            return Self::from_base64(&encoded).map(Some);
+        }
+        if let Ok(path) = std::env::var(SECRET_KEY_FILE_ENV) {
+            return Self::from_file(path).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Returns the key encoded as base64, suitable for `from_base64`.
+    pub fn to_base64(&self) -> String {
+        STANDARD.encode(self.0)
+    }
+
+    /// Encrypts `plaintext` into an `enc:v1:` value.
+    pub fn encrypt(&self, plaintext: &str) -> String {
+        let cipher = ChaCha20Poly1305::new(&self.0);
+        let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .expect("encrypting an in-memory buffer cannot fail");
+
+        let mut payload = nonce.to_vec();
+        payload.extend(ciphertext);
+        format!("{}{}", ENCRYPTED_PREFIX, STANDARD.encode(payload))
+    }
+
+    /// Decrypts an `enc:v1:` value.
+    ///
+    /// `field` is only used to make error messages point at the right value.
+    pub fn decrypt(&self, field: &str, value: &str) -> Result<String, ConfigError> {
+        let error = |reason: &str| ConfigError::Decryption {
+            field: field.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let encoded = value
+            .strip_prefix(ENCRYPTED_PREFIX)
+            .ok_or_else(|| error("value does not start with `enc:v1:`"))?;
+        let payload = STANDARD
+            .decode(encoded)
+            .map_err(|_| error("payload is not valid base64"))?;
+        if payload.len() < NONCE_LEN {
+            return Err(error("payload is too short"));
+        }
+
+        let (nonce, ciphertext) = payload.split_at(NONCE_LEN);
+        let plaintext = ChaCha20Poly1305::new(&self.0)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| error("wrong key or corrupted ciphertext"))?;
+        String::from_utf8(plaintext).map_err(|_| error("plaintext is not valid UTF-8"))
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey({})", REDACTED)
+    }
+}
+
+/// Decrypts every encrypted value in `config.secrets` in place.
+///
+/// If no key is given, one is read with `SecretKey::from_env`, but only
+/// when the configuration actually contains an encrypted value.
+///
+/// Encrypted values anywhere else, e.g. in a section or a flag, fail with
+/// `ConfigError::Decryption`: they would otherwise be used as ciphertext,
+/// and once decrypted would not be redacted.
+pub fn decrypt_secrets(config: &mut AppConfig, key: Option<&SecretKey>) -> Result<(), ConfigError> {
+    reject_encrypted_outside_secrets(config)?;
+    let encrypted: Vec<String> = config
+        .secrets
+        .iter()
+        .filter(|(_, value)| is_encrypted(value.expose()))
+        .map(|(name, _)| name.clone())
+        .collect();
+    if encrypted.is_empty() {
+        return Ok(());
+    }
+
+    let env_key;
+    let key = match key {
+        Some(key) => key,
+        None => {
+            env_key = SecretKey::from_env()?.ok_or_else(|| {
+                ConfigError::InvalidSecretKey(format!(
+                    "the configuration contains encrypted values but neither {} nor {} is set",
+                    SECRET_KEY_ENV, SECRET_KEY_FILE_ENV
+                ))
+            })?;
+            &env_key
+        }
+    };
+
+    for name in encrypted {
+        let field = format!("secrets.{}", name);
+        let plaintext = key.decrypt(&field, config.secrets[&name].expose())?;
+        config.secrets.insert(name, Secret::decrypted(plaintext));
+    }
+    Ok(())
+}
+
+/// Fails if a value outside `config.secrets` is encrypted.
+fn reject_encrypted_outside_secrets(config: &AppConfig) -> Result<(), ConfigError> {
+    let mut document =
+        serde_json::to_value(config).map_err(|e| ConfigError::Serialization(e.to_string()))?;
+    if let Some(table) = document.as_object_mut() {
+        table.remove("secrets");
+    }
+    match find_encrypted(&document, String::new()) {
+        Some(field) => Err(ConfigError::Decryption {
+            field,
+            reason: "encrypted values are only supported in the [secrets] table".to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Returns the dotted path of the first encrypted string in `value`.
+fn find_encrypted(value: &serde_json::Value, path: String) -> Option<String> {
+    let child = |key: &dyn fmt::Display| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+    match value {
+        serde_json::Value::String(s) if is_encrypted(s) => Some(path),
+        serde_json::Value::Object(table) => table
+            .iter()
+            .find_map(|(key, value)| find_encrypted(value, child(key))),
+        serde_json::Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, value)| find_encrypted(value, child(&i))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_is_redacted() {
+        let secret = Secret::new("hunter2".to_string());
+
+        assert_eq!(format!("{:?}", secret), "[REDACTED]");
+        assert_eq!(secret.to_string(), "[REDACTED]");
+        assert_eq!(serde_json::to_string(&secret).unwrap(), r#""[REDACTED]""#);
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn test_secret_deserializes_plain_value() {
+        let secret: Secret<String> = serde_json::from_str(r#""hunter2""#).unwrap();
+        assert_eq!(secret.into_inner(), "hunter2");
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_roundtrip() {
+        let key = SecretKey::generate();
+        let encrypted = key.encrypt("s3cr3t-bank-token");
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("s3cr3t"));
+        assert_ne!(encrypted, key.encrypt("s3cr3t-bank-token"));
+        assert_eq!(key.decrypt("secrets.bank", &encrypted).unwrap(), "s3cr3t-bank-token");
+    }
+
+    #[test]
+    fn test_decrypt_with_wrong_key() {
+        let encrypted = SecretKey::generate().encrypt("value");
+        let result = SecretKey::generate().decrypt("secrets.db_password", &encrypted);
+
+        assert!(matches!(
+            result,
+            Err(ConfigError::Decryption { ref field, .. }) if field == "secrets.db_password"
+        ));
+    }
+
+    #[test]
+    fn test_key_roundtrip_and_validation() {
+        let key = SecretKey::generate();
+        let decoded = SecretKey::from_base64(&key.to_base64()).unwrap();
+        assert_eq!(decoded.decrypt("x", &key.encrypt("value")).unwrap(), "value");
+
+        assert!(matches!(
+            SecretKey::from_base64("c2hvcnQ="),
+            Err(ConfigError::InvalidSecretKey(_))
+        ));
+        assert!(matches!(
+            SecretKey::from_base64("not base64!"),
+            Err(ConfigError::InvalidSecretKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_decrypt_secrets_in_config() {
+        let key = SecretKey::generate();
+        let mut config = AppConfig::default();
+        config
+            .secrets
+            .insert("db_password".to_string(), Secret::new(key.encrypt("pa55")));
+        config
+            .secrets
+            .insert("plain".to_string(), Secret::new("not encrypted".to_string()));
+
+        decrypt_secrets(&mut config, Some(&key)).unwrap();
+
+        assert_eq!(config.secrets["db_password"].expose(), "pa55");
+        assert_eq!(config.secrets["plain"].expose(), "not encrypted");
+    }
+
+    #[test]
+    fn test_rejects_encrypted_values_outside_secrets() {
+        let key = SecretKey::generate();
+        let mut config: AppConfig = toml::from_str(&format!(
+            "[database]\nurl = \"{}\"\n",
+            key.encrypt("postgres://")
+        ))
+        .unwrap();
+
+        match decrypt_secrets(&mut config, Some(&key)) {
+            Err(ConfigError::Decryption { field, reason }) => {
+                assert_eq!(field, "database.url");
+                assert!(reason.contains("[secrets]"), "{}", reason);
+            }
+            other => panic!("expected a decryption error, got {:?}", other),
+        }
+    }
+}
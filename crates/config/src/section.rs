@@ -0,0 +1,215 @@
+//! Typed configuration sections owned by other crates.
+//!
+//! Any top-level table that is not one of the built-in `AppConfig` fields,
+//! e.g. `[monitoring]`, is kept as an untyped value in `AppConfig::sections`.
+//! Other unknown top-level values, such as a misspelled `log_levl = "debug"`,
+//! are ignored like any other unknown key; strict mode reports them.
+//!
+//! There is no registry of sections: a crate declares the shape of its
+//! table by implementing `ConfigSection` and reads it back with
+//! `AppConfig::section`, and nothing stops two crates from claiming the
+//! same name. A section only takes part in validation and in the JSON
+//! Schema when it is added explicitly, with `SectionRule` and
+//! `SchemaBuilder::with_section`.
+//!
+//! ```
+//! use config::{section::ConfigSection, types::AppConfig};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+//! struct MonitoringSection {
+//!     endpoint: Option<String>,
+//!     interval_secs: Option<u64>,
+//! }
+//!
+//! impl ConfigSection for MonitoringSection {
+//!     const NAME: &'static str = "monitoring";
+//! }
+//!
+//! let config: AppConfig = toml::from_str("[monitoring]\ninterval_secs = 30\n").unwrap();
+//! let monitoring: MonitoringSection = config.section().unwrap().unwrap();
+//! assert_eq!(monitoring.interval_secs, Some(30));
+//! ```
+//!
+//! Sections are deep-merged across layers: tables are merged key by key and
+//! any other value set by a later layer replaces the earlier one.
+
+use crate::{
+    types::AppConfig,
+    validation::{ValidationFailure, ValidationRule},
+};
+use serde::{
+    de::DeserializeOwned,
+    Deserialize, Deserializer, Serialize,
+};
+use serde_json::Value;
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// A strongly typed top-level table of the configuration.
+pub trait ConfigSection: Serialize + DeserializeOwned {
+    /// The name of the table, e.g. `monitoring` for `[monitoring]`.
+    const NAME: &'static str;
+}
+
+/// Deserializes `AppConfig::sections`, dropping top-level values that are
+/// not tables.
+pub(crate) fn deserialize_sections<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut sections = BTreeMap::<String, Value>::deserialize(deserializer)?;
+    sections.retain(|_, value| value.is_object());
+    Ok(sections)
+}
+
+/// Merges `overlay` into `base`, recursing into tables that exist in both.
+pub(crate) fn merge_values(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Flattens a value into `(dotted path, rendered value)` pairs, one per
+/// leaf. Strings are rendered without quotes and arrays as JSON.
+pub(crate) fn leaf_fields(path: String, value: &Value, fields: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(table) => {
+            for (key, value) in table {
+                leaf_fields(format!("{}.{}", path, key), value, fields);
+            }
+        }
+        Value::String(s) => fields.push((path, s.clone())),
+        value => fields.push((path, value.to_string())),
+    }
+}
+
+/// A validation rule that fails when the `T::NAME` section is present but
+/// does not deserialize into `T`.
+///
+/// Registering it with a `Validator` lets a crate reject a malformed section
+/// at load time instead of when it first reads it.
+pub struct SectionRule<T> {
+    name: String,
+    section: PhantomData<fn() -> T>,
+}
+
+impl<T: ConfigSection> SectionRule<T> {
+    /// Creates a rule named `section-<NAME>`.
+    pub fn new() -> Self {
+        Self {
+            name: format!("section-{}", T::NAME),
+            section: PhantomData,
+        }
+    }
+}
+
+impl<T: ConfigSection> Default for SectionRule<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ConfigSection> ValidationRule for SectionRule<T> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        match config.section::<T>() {
+            Err(e) => vec![ValidationFailure::new(self.name(), T::NAME, e.to_string())],
+            Ok(_) => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validation::Validator;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+    struct Monitoring {
+        endpoint: Option<String>,
+        interval_secs: Option<u64>,
+    }
+
+    impl ConfigSection for Monitoring {
+        const NAME: &'static str = "monitoring";
+    }
+
+    #[test]
+    fn test_merge_values_is_deep() {
+        let mut base = json!({ "http": { "port": 80, "host": "localhost" }, "tags": ["a"] });
+        merge_values(
+            &mut base,
+            json!({ "http": { "port": 8080 }, "tags": ["b"], "debug": true }),
+        );
+
+        assert_eq!(
+            base,
+            json!({
+                "http": { "port": 8080, "host": "localhost" },
+                "tags": ["b"],
+                "debug": true,
+            })
+        );
+    }
+
+    #[test]
+    fn test_typed_section_roundtrip() {
+        let mut config = AppConfig::default();
+        assert_eq!(config.section::<Monitoring>().unwrap(), None);
+
+        let monitoring = Monitoring {
+            endpoint: Some("https://metrics.internal".to_string()),
+            interval_secs: Some(15),
+        };
+        config.set_section(&monitoring).unwrap();
+
+        assert_eq!(config.section::<Monitoring>().unwrap(), Some(monitoring));
+        assert_eq!(
+            config.fields(),
+            vec![
+                (
+                    "monitoring.endpoint".to_string(),
+                    "https://metrics.internal".to_string()
+                ),
+                ("monitoring.interval_secs".to_string(), "15".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ignores_top_level_scalars() {
+        let config: AppConfig = toml::from_str("log_levl = \"debug\"\n").unwrap();
+        assert!(config.sections.is_empty());
+        let config: AppConfig =
+            serde_json::from_value(json!({ "monitoring": [1, 2], "logging": {} })).unwrap();
+        assert_eq!(config.sections.keys().collect::<Vec<_>>(), ["logging"]);
+    }
+
+    #[test]
+    fn test_section_rule_reports_malformed_section() {
+        let config: AppConfig = toml::from_str("[monitoring]\ninterval_secs = \"soon\"\n").unwrap();
+        let validator = Validator::new().with_rule(SectionRule::<Monitoring>::new());
+
+        let failures = validator.check(&config);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].rule, "section-monitoring");
+        assert_eq!(failures[0].field, "monitoring");
+    }
+}
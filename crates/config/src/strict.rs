@@ -0,0 +1,223 @@
+//! Detection of unknown keys for strict mode.
+//!
+//! The parser ignores keys it does not recognise, so a typo such as
+//! `log_levl = "debug"` silently has no effect. In strict mode a file is
+//! checked against the JSON Schema from the `schema` module and every key
+//! the schema does not describe is reported, together with the closest
+//! known key at the same level.
+
+use serde_json::Value;
+use std::{fmt, path::PathBuf};
+
+/// The minimum similarity for a known key to be suggested as a fix.
+const SUGGESTION_THRESHOLD: f64 = 0.7;
+
+/// A key that is not part of the configuration schema.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKey {
+    /// The dotted path of the key, e.g. `log_levl` or `monitoring.endpiont`.
+    pub key: String,
+    /// The file the key was found in.
+    pub path: PathBuf,
+    /// The 1-based line of the key, where it can be determined.
+    pub line: Option<usize>,
+    /// The closest known key at the same level, as a full dotted path.
+    pub suggestion: Option<String>,
+}
+
+impl fmt::Display for UnknownKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}: ", self.path.display(), line)?,
+            None => write!(f, "{}: ", self.path.display())?,
+        }
+        write!(f, "unknown key `{}`", self.key)?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, ", did you mean `{}`?", suggestion)?;
+        }
+        Ok(())
+    }
+}
+
+/// Every unknown key found in a configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnknownKeys(pub Vec<UnknownKey>);
+
+impl UnknownKeys {
+    /// Returns the individual keys.
+    pub fn keys(&self) -> &[UnknownKey] {
+        &self.0
+    }
+}
+
+impl fmt::Display for UnknownKeys {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", keys.join("; "))
+    }
+}
+
+/// Returns the dotted paths of every key in `document` that `schema` does
+/// not describe, each paired with a suggested replacement.
+///
+/// Only tables whose schema lists `properties` are checked key by key; maps
+/// such as `feature_flags` accept any key and are checked value by value.
+pub(crate) fn unknown_keys(document: &Value, schema: &Value) -> Vec<(String, Option<String>)> {
+    let mut unknown = Vec::new();
+    walk(document, schema, schema, "", &mut unknown);
+    unknown
+}
+
+fn walk(
+    value: &Value,
+    schema: &Value,
+    root: &Value,
+    prefix: &str,
+    unknown: &mut Vec<(String, Option<String>)>,
+) {
+    let Value::Object(table) = value else {
+        return;
+    };
+    let schema = resolve(schema, root);
+    let path = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+
+    if let Some(Value::Object(properties)) = schema.get("properties") {
+        for (key, value) in table {
+            match properties.get(key) {
+                Some(property) => walk(value, property, root, &path(key), unknown),
+                None => {
+                    let suggestion = closest(key, properties.keys()).map(&path);
+                    unknown.push((path(key), suggestion));
+                }
+            }
+        }
+    } else if let Some(item @ Value::Object(_)) = schema.get("additionalProperties") {
+        for (key, value) in table {
+            walk(value, item, root, &path(key), unknown);
+        }
+    }
+}
+
+/// Follows `$ref`s and picks the non-null branch of `Option` fields, which
+/// are generated as `anyOf: [{ $ref }, { type: null }]`.
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    if let Some(Value::String(reference)) = schema.get("$ref") {
+        if let Some(target) = reference
+            .strip_prefix('#')
+            .and_then(|pointer| root.pointer(pointer))
+        {
+            return resolve(target, root);
+        }
+    }
+    if let Some(Value::Array(branches)) = schema.get("anyOf") {
+        if let Some(branch) = branches
+            .iter()
+            .find(|branch| branch.get("type") != Some(&Value::from("null")))
+        {
+            return resolve(branch, root);
+        }
+    }
+    schema
+}
+
+/// Returns the candidate most similar to `key`, if any is similar enough to
+/// be a likely typo.
+pub(crate) fn closest<'a>(key: &str, known: impl Iterator<Item = &'a String>) -> Option<&'a str> {
+    known
+        .map(|candidate| {
+            let score = strsim::normalized_damerau_levenshtein(key, candidate);
+            (candidate, score)
+        })
+        .filter(|(_, score)| *score >= SUGGESTION_THRESHOLD)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(candidate, _)| candidate.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        schema::{app_config_schema, SchemaBuilder},
+        section::ConfigSection,
+    };
+    use schemars::JsonSchema;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Serialize, Deserialize, JsonSchema)]
+    struct Monitoring {
+        endpoint: Option<String>,
+    }
+
+    impl ConfigSection for Monitoring {
+        const NAME: &'static str = "monitoring";
+    }
+
+    #[test]
+    fn test_reports_unknown_keys_with_suggestions() {
+        let document = json!({
+            "log_levl": "debug",
+            "log_format": "json",
+            "feature_flags": { "anything": true },
+            "secrets": { "db_password": "x" },
+            "zzz": 1,
+        });
+
+        assert_eq!(
+            unknown_keys(&document, &app_config_schema()),
+            vec![
+                ("log_levl".to_string(), Some("log_level".to_string())),
+                ("zzz".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_checks_flag_tables() {
+        let document = json!({
+            "feature_flags": { "dark_mode": true, "new_ui": { "rollot": 0.5 } },
+        });
+
+        assert_eq!(
+            unknown_keys(&document, &app_config_schema()),
+            vec![(
+                "feature_flags.new_ui.rollot".to_string(),
+                Some("feature_flags.new_ui.rollout".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_checks_registered_sections() {
+        let schema = SchemaBuilder::new().with_section::<Monitoring>().build();
+        let document = json!({ "monitoring": { "endpiont": "http://localhost" } });
+
+        assert_eq!(
+            unknown_keys(&document, &schema),
+            vec![(
+                "monitoring.endpiont".to_string(),
+                Some("monitoring.endpoint".to_string())
+            )]
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let key = UnknownKey {
+            key: "log_levl".to_string(),
+            path: PathBuf::from("ciphr.toml"),
+            line: Some(2),
+            suggestion: Some("log_level".to_string()),
+        };
+        assert_eq!(
+            key.to_string(),
+            "ciphr.toml:2: unknown key `log_levl`, did you mean `log_level`?"
+        );
+    }
+}
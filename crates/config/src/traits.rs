@@ -1,11 +1,18 @@
 use crate::errors::ConfigError;
+use crate::provenance::{Provenance, ValueSource};
 use crate::types::AppConfig;
+#[cfg(feature = "remote")]
+use async_trait::async_trait;
+use std::path::PathBuf;
 
 /// A trait for providing application configuration.
 ///
 /// This allows for a generic interface to load configuration from different
 /// sources, such as files, environment variables, or remote services.
-pub trait ConfigurationProvider {
+///
+/// Providers must be `Send + Sync` so that they can be shared with
+/// background tasks such as the `ConfigWatcher`.
+pub trait ConfigurationProvider: Send + Sync {
     /// Loads the configuration.
     ///
     /// Returns an `AppConfig` instance if the provider can successfully
@@ -21,12 +28,66 @@ pub trait ConfigurationProvider {
     /// # Returns
     /// An empty `Result` or a `ConfigError` if validation fails.
     fn validate(&self, config: &AppConfig) -> Result<(), ConfigError>;
+
+    /// Returns the files this provider reads from.
+    ///
+    /// Watchers use this to decide when a reload is needed. Providers that
+    /// are not backed by files can rely on the default, which watches
+    /// nothing.
+    fn watch_paths(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    /// Returns a short, human-readable name for this provider.
+    ///
+    /// The name is used in provenance reports when no more precise location
+    /// is available.
+    fn name(&self) -> String {
+        let type_name = std::any::type_name::<Self>();
+        type_name.rsplit("::").next().unwrap_or(type_name).to_string()
+    }
+
+    /// Loads the configuration together with the source of each field.
+    ///
+    /// The default implementation attributes every field to `name()`.
+    /// File-based providers override it to report paths and line numbers.
+    fn load_with_provenance(&self) -> Result<Option<(AppConfig, Provenance)>, ConfigError> {
+        Ok(self.load()?.map(|config| {
+            let provenance = Provenance::from_config(&config, |_| ValueSource::named(self.name()));
+            (config, provenance)
+        }))
+    }
+}
+
+/// The asynchronous counterpart of `ConfigurationProvider`, for sources
+/// that load over the network, such as `HttpConfigurationProvider`.
+///
+/// Local and remote sources can be stacked together in an
+/// `AsyncLayeredConfigurationProvider`, which accepts providers of either
+/// kind.
+///
+/// Requires the `remote` feature.
+#[cfg(feature = "remote")]
+#[async_trait]
+pub trait AsyncConfigurationProvider: Send + Sync {
+    /// Loads the configuration, returning `Ok(None)` if the source does not
+    /// exist.
+    async fn load(&self) -> Result<Option<AppConfig>, ConfigError>;
+
+    /// Validates the loaded configuration.
+    fn validate(&self, config: &AppConfig) -> Result<(), ConfigError>;
+
+    /// Returns a short, human-readable name for this provider.
+    fn name(&self) -> String;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::types::{AppConfig, LogFormat, LogLevel};
+    use crate::{
+        types::{AppConfig, LogFormat, LogLevel},
+        validation::{NonEmptyEnvironment, Validator},
+    };
 
     struct MockProvider {
         config: AppConfig,
@@ -38,14 +99,9 @@ mod tests {
         }
 
         fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
-            if let Some(env) = &config.environment {
-                if env.is_empty() {
-                    return Err(ConfigError::ValidationError {
-                        field: "environment".to_string(),
-                    });
-                }
-            }
-            Ok(())
+            Validator::new()
+                .with_rule(NonEmptyEnvironment)
+                .validate(config)
         }
     }
 
@@ -55,7 +111,7 @@ mod tests {
             environment: Some("test".to_string()),
             log_level: Some(LogLevel::Debug),
             log_format: Some(LogFormat::Json),
-            feature_flags: Default::default(),
+            ..Default::default()
         };
 
         let provider = MockProvider {
@@ -66,6 +122,7 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::field_reassign_with_default)]
     fn test_mock_provider_validate() {
         let provider = MockProvider {
             config: AppConfig::default(),
@@ -74,9 +131,6 @@ mod tests {
         let mut invalid_config = AppConfig::default();
         invalid_config.environment = Some("".to_string());
         let result = provider.validate(&invalid_config);
-        assert!(matches!(
-            result,
-            Err(ConfigError::ValidationError { .. })
-        ));
+        assert!(matches!(result, Err(ConfigError::Validation(_))));
     }
 } 
\ No newline at end of file
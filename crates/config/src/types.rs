@@ -1,23 +1,26 @@
+use crate::{
+    errors::ConfigError,
+    flag::FlagDefinition,
+    secret::Secret,
+    section::{deserialize_sections, leaf_fields, ConfigSection},
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+/// Defines the possible log levels.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq, Eq, Default)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Trace,
     Debug,
+    #[default]
     Info,
     Warn,
     Error,
 }
 
-impl Default for LogLevel {
-    fn default() -> Self {
-        LogLevel::Info
-    }
-}
-
 impl fmt::Display for LogLevel {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -31,23 +34,119 @@ impl fmt::Display for LogLevel {
 }
 
 /// Defines the possible log output formats.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum LogFormat {
     Text,
     Json,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Default)]
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema, Default)]
 pub struct AppConfig {
-    #[serde(default)]
+    /// The layout version the file was written for; see the `migration`
+    /// module. Files without one are version 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_version: Option<u32>,
+    /// Other files to load on top of this one, as paths or glob patterns
+    /// relative to this file, e.g. `["conf.d/*.toml"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// The deployment environment, e.g. `production`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub environment: Option<String>,
-    #[serde(default)]
+    /// The minimum level of log events to record.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub log_level: Option<LogLevel>,
-    #[serde(default)]
+    /// How log events are written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub log_format: Option<LogFormat>,
-    #[serde(default)]
-    pub feature_flags: HashMap<String, bool>,
+    /// Feature flags, keyed by name. Each is either `true`/`false` or a
+    /// table with rollout settings; see the `flag` module.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub feature_flags: HashMap<String, FlagDefinition>,
+    /// Sensitive values such as passwords and API keys. Values may be stored
+    /// encrypted as `enc:v1:…` and are decrypted at load time.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub secrets: HashMap<String, Secret<String>>,
+    /// Every other top-level table, keyed by name, e.g. `[monitoring]`.
+    /// Read them with `section` rather than directly. Other unknown
+    /// top-level values are ignored.
+    #[serde(flatten, deserialize_with = "deserialize_sections")]
+    pub sections: BTreeMap<String, serde_json::Value>,
+}
+
+impl AppConfig {
+    /// Returns every field that has a value, as dotted paths paired with the
+    /// value rendered as a string.
+    ///
+    /// Top-level fields come first in declaration order, followed by the
+    /// feature flags sorted by name, e.g. `feature_flags.new_ui`, the leaves
+    /// of each section, e.g. `monitoring.interval_secs`, and the secrets
+    /// sorted by name. Secret values are rendered redacted.
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        if let Some(environment) = &self.environment {
+            fields.push(("environment".to_string(), environment.clone()));
+        }
+        if let Some(log_level) = &self.log_level {
+            fields.push(("log_level".to_string(), log_level.to_string()));
+        }
+        if let Some(log_format) = &self.log_format {
+            fields.push(("log_format".to_string(), log_format.to_string()));
+        }
+
+        let mut flags: Vec<_> = self.feature_flags.iter().collect();
+        flags.sort_by_key(|(name, _)| *name);
+        for (name, flag) in flags {
+            fields.push((format!("feature_flags.{}", name), flag.to_string()));
+        }
+
+        for (name, value) in &self.sections {
+            leaf_fields(name.clone(), value, &mut fields);
+        }
+
+        let mut secrets: Vec<_> = self.secrets.iter().collect();
+        secrets.sort_by_key(|(name, _)| *name);
+        for (name, value) in secrets {
+            fields.push((format!("secrets.{}", name), value.to_string()));
+        }
+        fields
+    }
+
+    /// Returns the `T::NAME` section, or `Ok(None)` if no layer set it.
+    ///
+    /// Fails with `ConfigError::InvalidSection` if the section does not
+    /// match the shape of `T`.
+    pub fn section<T: ConfigSection>(&self) -> Result<Option<T>, ConfigError> {
+        self.sections
+            .get(T::NAME)
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()
+            .map_err(|source| ConfigError::InvalidSection {
+                section: T::NAME.to_string(),
+                source,
+            })
+    }
+
+    /// Stores `section` as the `T::NAME` section, replacing any existing one.
+    pub fn set_section<T: ConfigSection>(&mut self, section: &T) -> Result<(), ConfigError> {
+        let value =
+            serde_json::to_value(section).map_err(|source| ConfigError::InvalidSection {
+                section: T::NAME.to_string(),
+                source,
+            })?;
+        self.sections.insert(T::NAME.to_string(), value);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -62,4 +161,27 @@ mod tests {
         assert_eq!(default_config.log_format, None);
         assert!(default_config.feature_flags.is_empty());
     }
+
+    #[test]
+    fn test_fields_lists_set_values() {
+        let mut config = AppConfig {
+            log_level: Some(LogLevel::Warn),
+            ..Default::default()
+        };
+        config
+            .feature_flags
+            .insert("new_ui".to_string(), FlagDefinition::new(true));
+        config
+            .feature_flags
+            .insert("beta".to_string(), FlagDefinition::new(false));
+
+        assert_eq!(
+            config.fields(),
+            vec![
+                ("log_level".to_string(), "warn".to_string()),
+                ("feature_flags.beta".to_string(), "false".to_string()),
+                ("feature_flags.new_ui".to_string(), "true".to_string()),
+            ]
+        );
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,667 @@
+//! Composable validation rules for `AppConfig`.
+//!
+//! A `Validator` holds a list of named `ValidationRule`s and runs every one
+//! of them against a configuration, collecting all failures into a single
+//! `ConfigError::Validation` instead of stopping at the first problem.
+
+use crate::{
+    errors::ConfigError,
+    strict::closest,
+    types::{AppConfig, LogLevel},
+};
+use std::fmt;
+
+/// A single rule violation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationFailure {
+    /// The name of the rule that failed.
+    pub rule: String,
+    /// The dotted path of the offending field, e.g. `feature_flags.New_UI`.
+    pub field: String,
+    /// A human-readable description of the problem.
+    pub message: String,
+    /// A hint on how to fix the problem, shown in diagnostics.
+    pub help: Option<String>,
+}
+
+impl ValidationFailure {
+    /// Creates a new failure.
+    pub fn new(
+        rule: impl Into<String>,
+        field: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        Self {
+            rule: rule.into(),
+            field: field.into(),
+            message: message.into(),
+            help: None,
+        }
+    }
+
+    /// Adds a hint on how to fix the problem.
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+}
+
+impl fmt::Display for ValidationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} [{}]", self.field, self.message, self.rule)
+    }
+}
+
+/// Every failure found while validating a configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ValidationErrors(pub Vec<ValidationFailure>);
+
+impl ValidationErrors {
+    /// Returns the individual failures.
+    pub fn failures(&self) -> &[ValidationFailure] {
+        &self.0
+    }
+
+    /// Returns the failures reported for a given field.
+    pub fn for_field<'a>(&'a self, field: &'a str) -> impl Iterator<Item = &'a ValidationFailure> {
+        self.0.iter().filter(move |failure| failure.field == field)
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures: Vec<String> = self.0.iter().map(ToString::to_string).collect();
+        write!(f, "{}", failures.join("; "))
+    }
+}
+
+/// A named check that can be registered with a `Validator`.
+pub trait ValidationRule: Send + Sync {
+    /// A short, unique, kebab-case name identifying the rule.
+    fn name(&self) -> &str;
+
+    /// Checks the configuration and returns every violation found.
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure>;
+}
+
+/// A rule backed by a closure, for checks that do not warrant their own type.
+pub struct FnRule<F> {
+    name: String,
+    check: F,
+}
+
+impl<F> FnRule<F>
+where
+    F: Fn(&AppConfig) -> Vec<ValidationFailure> + Send + Sync,
+{
+    /// Creates a rule called `name` that runs `check`.
+    pub fn new(name: impl Into<String>, check: F) -> Self {
+        Self {
+            name: name.into(),
+            check,
+        }
+    }
+}
+
+impl<F> ValidationRule for FnRule<F>
+where
+    F: Fn(&AppConfig) -> Vec<ValidationFailure> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        (self.check)(config)
+    }
+}
+
+/// Rejects an `environment` that is set but empty.
+pub struct NonEmptyEnvironment;
+
+impl ValidationRule for NonEmptyEnvironment {
+    fn name(&self) -> &str {
+        "non-empty-environment"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        match &config.environment {
+            Some(env) if env.is_empty() => {
+                vec![
+                    ValidationFailure::new(self.name(), "environment", "must not be empty")
+                        .with_help("set a name such as `production`, or remove the setting"),
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Requires `environment`, when set, to be one of a fixed set of names.
+///
+/// This rule is not part of `Validator::default()`, which accepts any
+/// environment name. Register it to restrict the names, either to
+/// `AllowedEnvironments::DEFAULT` with `AllowedEnvironments::default()` or
+/// to your own list with `AllowedEnvironments::new`.
+pub struct AllowedEnvironments {
+    allowed: Vec<String>,
+}
+
+impl AllowedEnvironments {
+    /// The environments accepted by `AllowedEnvironments::default()`.
+    pub const DEFAULT: [&'static str; 4] = ["development", "test", "staging", "production"];
+
+    /// Creates a rule that accepts only the given environment names.
+    pub fn new<I, S>(allowed: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            allowed: allowed.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl Default for AllowedEnvironments {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT)
+    }
+}
+
+impl ValidationRule for AllowedEnvironments {
+    fn name(&self) -> &str {
+        "allowed-environment"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        match &config.environment {
+            // Empty values are reported by `NonEmptyEnvironment`.
+            Some(env) if !env.is_empty() && !self.allowed.contains(env) => {
+                let failure = ValidationFailure::new(
+                    self.name(),
+                    "environment",
+                    format!("`{}` is not one of {}", env, self.allowed.join(", ")),
+                );
+                match closest(env, self.allowed.iter()) {
+                    Some(allowed) => {
+                        vec![failure.with_help(format!("did you mean `{}`?", allowed))]
+                    }
+                    None => vec![failure],
+                }
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Requires feature flag names to be kebab-case, as the design document
+/// specifies (e.g. `new-ui`, not `new_ui` or `NewUI`).
+///
+/// This rule is not part of `Validator::default()` because existing
+/// configurations and the `CIPHR_FEATURE_FLAGS__<NAME>` environment
+/// variables use snake_case names. Register it explicitly to enforce it.
+pub struct KebabCaseFlagNames;
+
+fn is_kebab_case(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--")
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+}
+
+impl ValidationRule for KebabCaseFlagNames {
+    fn name(&self) -> &str {
+        "kebab-case-flag-names"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        let mut names: Vec<&String> = config.feature_flags.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .filter(|name| !is_kebab_case(name))
+            .map(|name| {
+                ValidationFailure::new(
+                    self.name(),
+                    format!("feature_flags.{}", name),
+                    "feature flag names must be kebab-case",
+                )
+                .with_help(format!(
+                    "rename it to `{}`",
+                    name.to_lowercase().replace('_', "-")
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Forbids `log_level = "trace"` when `environment` is `production`.
+///
+/// This rule is not part of `Validator::default()`, since configurations
+/// that were valid before it existed may trace in production on purpose.
+/// Register it explicitly to enforce it.
+pub struct NoTraceInProduction;
+
+impl ValidationRule for NoTraceInProduction {
+    fn name(&self) -> &str {
+        "no-trace-in-production"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        if config.environment.as_deref() == Some("production")
+            && config.log_level == Some(LogLevel::Trace)
+        {
+            vec![ValidationFailure::new(
+                self.name(),
+                "log_level",
+                "`trace` logging is not allowed in production",
+            )
+            .with_help("use `debug` or a less verbose level")]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Requires every flag's `rollout` to be a fraction between `0.0` and `1.0`.
+pub struct FlagRolloutRange;
+
+impl ValidationRule for FlagRolloutRange {
+    fn name(&self) -> &str {
+        "flag-rollout"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        let mut flags: Vec<_> = config.feature_flags.iter().collect();
+        flags.sort_by_key(|(name, _)| *name);
+        flags
+            .into_iter()
+            .filter_map(|(name, flag)| Some((name, flag.rollout?)))
+            .filter(|(_, rollout)| !(0.0..=1.0).contains(rollout))
+            .map(|(name, rollout)| {
+                ValidationFailure::new(
+                    self.name(),
+                    format!("feature_flags.{}.rollout", name),
+                    format!("{} is not between 0.0 and 1.0", rollout),
+                )
+                .with_help("use a fraction of users, e.g. `0.25` for 25%")
+            })
+            .collect()
+    }
+}
+
+/// Requires the variants of a multivariate flag to have a total weight
+/// above zero, so that every user can be assigned one.
+pub struct FlagVariantWeights;
+
+impl ValidationRule for FlagVariantWeights {
+    fn name(&self) -> &str {
+        "flag-variants"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        let mut flags: Vec<_> = config.feature_flags.iter().collect();
+        flags.sort_by_key(|(name, _)| *name);
+        flags
+            .into_iter()
+            .filter(|(_, flag)| !flag.variants.is_empty())
+            .filter(|(_, flag)| flag.variants.iter().all(|variant| variant.weight == 0))
+            .map(|(name, _)| {
+                ValidationFailure::new(
+                    self.name(),
+                    format!("feature_flags.{}.variants", name),
+                    "every variant has a weight of 0",
+                )
+                .with_help("give at least one variant a positive `weight`")
+            })
+            .collect()
+    }
+}
+
+/// Requires the operands of every flag's targeting `rule` to be usable, e.g.
+/// `matches` to be a valid regular expression; see `Rule::check`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FlagRules;
+
+impl ValidationRule for FlagRules {
+    fn name(&self) -> &str {
+        "flag-rules"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        let mut flags: Vec<_> = config.feature_flags.iter().collect();
+        flags.sort_by_key(|(name, _)| *name);
+        flags
+            .into_iter()
+            .filter_map(|(name, flag)| {
+                let message = flag.rule.as_ref()?.check().err()?;
+                Some(ValidationFailure::new(
+                    self.name(),
+                    format!("feature_flags.{}.rule", name),
+                    message,
+                ))
+            })
+            .collect()
+    }
+}
+
+/// Requires fields to be set rather than left out, either always or only in
+/// some environments.
+///
+/// Fields are dotted paths as in `AppConfig::fields`. A table such as
+/// `monitoring` counts as set if any of its keys is. This rule is not part
+/// of `Validator::default()`; `AppConfigBuilder::require` and `require_in`
+/// use it to check the fields that were set before defaults are filled in.
+#[derive(Debug, Clone, Default)]
+pub struct RequiredFields {
+    required: Vec<(String, Option<String>)>,
+}
+
+impl RequiredFields {
+    /// Creates a rule that requires nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requires `field` to be set in every environment.
+    pub fn require(mut self, field: impl Into<String>) -> Self {
+        self.required.push((field.into(), None));
+        self
+    }
+
+    /// Requires `field` to be set when `environment` is `environment`.
+    pub fn require_in(mut self, environment: impl Into<String>, field: impl Into<String>) -> Self {
+        self.required.push((field.into(), Some(environment.into())));
+        self
+    }
+}
+
+impl ValidationRule for RequiredFields {
+    fn name(&self) -> &str {
+        "required-fields"
+    }
+
+    fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        let set: Vec<String> = config
+            .fields()
+            .into_iter()
+            .map(|(field, _)| field)
+            .collect();
+        let is_set = |field: &str| {
+            set.iter().any(|path| {
+                path.strip_prefix(field)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+            })
+        };
+        self.required
+            .iter()
+            .filter(|(_, environment)| {
+                environment.is_none() || config.environment.as_ref() == environment.as_ref()
+            })
+            .filter(|(field, _)| !is_set(field))
+            .map(|(field, environment)| {
+                let message = match environment {
+                    Some(environment) => format!("must be set in {}", environment),
+                    None => "must be set".to_string(),
+                };
+                ValidationFailure::new(self.name(), field.clone(), message)
+                    .with_help(format!("set `{}` explicitly", field))
+            })
+            .collect()
+    }
+}
+
+/// A registry of validation rules.
+///
+/// `Validator::default()` contains the built-in rules
+/// (`NonEmptyEnvironment`, `FlagRolloutRange`, `FlagVariantWeights` and
+/// `FlagRules`).
+/// Use `Validator::new()` to start from an empty registry.
+pub struct Validator {
+    rules: Vec<Box<dyn ValidationRule>>,
+}
+
+impl Validator {
+    /// Creates a validator with no rules.
+    pub fn new() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Adds a rule. A rule with the same name replaces the existing one.
+    pub fn with_rule(mut self, rule: impl ValidationRule + 'static) -> Self {
+        self.rules.retain(|existing| existing.name() != rule.name());
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Removes the rule with the given name, if registered.
+    pub fn without_rule(mut self, name: &str) -> Self {
+        self.rules.retain(|rule| rule.name() != name);
+        self
+    }
+
+    /// Returns the names of the registered rules, in the order they run.
+    pub fn rule_names(&self) -> Vec<&str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
+    }
+
+    /// Runs every rule and returns all failures.
+    pub fn check(&self, config: &AppConfig) -> Vec<ValidationFailure> {
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.check(config))
+            .collect()
+    }
+
+    /// Runs every rule, returning a single `ConfigError::Validation` that
+    /// lists all failures if any rule is violated.
+    pub fn validate(&self, config: &AppConfig) -> Result<(), ConfigError> {
+        let failures = self.check(config);
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Validation(ValidationErrors(failures)))
+        }
+    }
+}
+
+impl Default for Validator {
+    fn default() -> Self {
+        Self::new()
+            .with_rule(NonEmptyEnvironment)
+            .with_rule(FlagRolloutRange)
+            .with_rule(FlagVariantWeights)
+            .with_rule(FlagRules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::AppConfigBuilder;
+
+    fn failures_of(result: Result<(), ConfigError>) -> Vec<ValidationFailure> {
+        match result {
+            Err(ConfigError::Validation(errors)) => errors.0,
+            other => panic!("expected validation errors, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_default_rules_accept_valid_config() {
+        let config = AppConfigBuilder::new()
+            .environment("production")
+            .log_level(LogLevel::Info)
+            .build();
+        assert!(Validator::default().validate(&config).is_ok());
+    }
+
+    #[test]
+    fn test_no_trace_in_production_is_opt_in() {
+        let config = AppConfigBuilder::new()
+            .environment("production")
+            .log_level(LogLevel::Trace)
+            .build();
+        assert!(Validator::default().validate(&config).is_ok());
+
+        let validator = Validator::default().with_rule(NoTraceInProduction);
+        let failures = failures_of(validator.validate(&config));
+        assert_eq!(failures[0].rule, "no-trace-in-production");
+    }
+
+    #[test]
+    fn test_collects_every_failure() {
+        let config = AppConfigBuilder::new()
+            .environment("production")
+            .log_level(LogLevel::Trace)
+            .feature_flag("New_UI", true)
+            .feature_flag("multi-currency", true)
+            .build();
+        let validator = Validator::default()
+            .with_rule(NoTraceInProduction)
+            .with_rule(KebabCaseFlagNames)
+            .with_rule(FnRule::new("flag-count", |config: &AppConfig| {
+                if config.feature_flags.len() > 1 {
+                    vec![ValidationFailure::new(
+                        "flag-count",
+                        "feature_flags",
+                        "at most one flag is allowed",
+                    )]
+                } else {
+                    Vec::new()
+                }
+            }));
+
+        let failures = failures_of(validator.validate(&config));
+        let fields: Vec<(&str, &str)> = failures
+            .iter()
+            .map(|f| (f.rule.as_str(), f.field.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("no-trace-in-production", "log_level"),
+                ("kebab-case-flag-names", "feature_flags.New_UI"),
+                ("flag-count", "feature_flags"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_environment_rules() {
+        let empty = AppConfigBuilder::new().environment("").build();
+        let failures = failures_of(Validator::default().validate(&empty));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].rule, "non-empty-environment");
+
+        let blank = AppConfigBuilder::new().environment(" ").build();
+        assert!(Validator::default().validate(&blank).is_ok());
+
+        let unknown = AppConfigBuilder::new().environment("qa").build();
+        assert!(Validator::default().validate(&unknown).is_ok());
+
+        let validator = Validator::default().with_rule(AllowedEnvironments::default());
+        let failures = failures_of(validator.validate(&unknown));
+        assert_eq!(failures[0].rule, "allowed-environment");
+
+        let validator = Validator::default().with_rule(AllowedEnvironments::new(["qa"]));
+        assert!(validator.validate(&unknown).is_ok());
+    }
+
+    #[test]
+    fn test_without_rule() {
+        let config = AppConfigBuilder::new()
+            .environment("production")
+            .log_level(LogLevel::Trace)
+            .build();
+        let validator = Validator::default()
+            .with_rule(NoTraceInProduction)
+            .without_rule("no-trace-in-production");
+        assert!(validator.validate(&config).is_ok());
+        assert_eq!(
+            validator.rule_names(),
+            vec![
+                "non-empty-environment",
+                "flag-rollout",
+                "flag-variants",
+                "flag-rules"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_flag_rollout_range() {
+        let flag = |rollout| crate::flag::FlagDefinition {
+            enabled: Some(true),
+            rollout: Some(rollout),
+            ..Default::default()
+        };
+        let config = AppConfigBuilder::new()
+            .feature_flag("half", flag(0.5))
+            .feature_flag("percent", flag(25.0))
+            .build();
+
+        let failures = failures_of(Validator::default().validate(&config));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].field, "feature_flags.percent.rollout");
+        assert_eq!(failures[0].message, "25 is not between 0.0 and 1.0");
+    }
+
+    #[test]
+    fn test_flag_variant_weights() {
+        let flag = |weights: &[u32]| crate::flag::FlagDefinition {
+            enabled: Some(true),
+            variants: weights
+                .iter()
+                .map(|weight| crate::flag::Variant::new("a", *weight))
+                .collect(),
+            ..Default::default()
+        };
+        let config = AppConfigBuilder::new()
+            .feature_flag("split", flag(&[0, 1]))
+            .feature_flag("unweighted", flag(&[0, 0]))
+            .build();
+
+        let failures = failures_of(Validator::default().validate(&config));
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].field, "feature_flags.unweighted.variants");
+    }
+
+    #[test]
+    fn test_kebab_case() {
+        assert!(is_kebab_case("new-ui"));
+        assert!(is_kebab_case("v2-reports"));
+        assert!(!is_kebab_case("new_ui"));
+        assert!(!is_kebab_case("NewUI"));
+        assert!(!is_kebab_case("-new"));
+        assert!(!is_kebab_case("new--ui"));
+        assert!(!is_kebab_case(""));
+    }
+
+    #[test]
+    fn test_required_fields() {
+        let rule = RequiredFields::new()
+            .require("log_level")
+            .require("monitoring")
+            .require_in("production", "log_format");
+
+        let mut config = AppConfig {
+            environment: Some("production".to_string()),
+            log_level: Some(LogLevel::Info),
+            ..Default::default()
+        };
+        let fields: Vec<String> = rule.check(&config).into_iter().map(|f| f.field).collect();
+        assert_eq!(fields, vec!["monitoring", "log_format"]);
+
+        config.sections.insert(
+            "monitoring".to_string(),
+            serde_json::json!({ "port": 9090 }),
+        );
+        config.environment = Some("staging".to_string());
+        assert!(rule.check(&config).is_empty());
+    }
+}
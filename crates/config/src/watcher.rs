@@ -0,0 +1,394 @@
+use crate::{
+    diff::{diff_configs, ConfigChange},
+    errors::ConfigError,
+    traits::ConfigurationProvider,
+    types::AppConfig,
+};
+use std::{
+    collections::hash_map::DefaultHasher,
+    collections::HashMap,
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+/// A new configuration published by a `ConfigWatcher`.
+#[derive(Debug, Clone)]
+pub struct ConfigUpdate {
+    /// The newly loaded and validated configuration.
+    pub config: AppConfig,
+    /// The fields that differ from the previously published configuration.
+    pub changes: Vec<ConfigChange>,
+}
+
+struct WatcherState {
+    provider: Box<dyn ConfigurationProvider>,
+    current: RwLock<AppConfig>,
+    /// Held for a whole reload, so that concurrent reloads publish in the
+    /// order they loaded.
+    reload: Mutex<()>,
+    fingerprints: Mutex<HashMap<PathBuf, Option<u64>>>,
+    subscribers: Mutex<Vec<Sender<ConfigUpdate>>>,
+}
+
+/// Watches the files behind a configuration provider and reloads them
+/// when they change.
+///
+/// A reloaded configuration is only published if it loads and validates
+/// successfully. If it does not, the watcher keeps serving the last good
+/// configuration. Cloning a `ConfigWatcher` is cheap; all clones share the
+/// same state and subscribers.
+#[derive(Clone)]
+pub struct ConfigWatcher {
+    state: Arc<WatcherState>,
+}
+
+/// Keeps a background polling thread alive.
+///
+/// The thread stops when the handle is dropped.
+pub struct WatchHandle {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Hashes the contents of a file, or returns `None` if it cannot be read.
+///
+/// For a directory, the names of its entries are hashed instead, so that
+/// adding or removing a file counts as a change.
+fn fingerprint(path: &Path) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    if path.is_dir() {
+        let mut names = fs::read_dir(path)
+            .ok()?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<Vec<_>, _>>()
+            .ok()?;
+        names.sort();
+        names.hash(&mut hasher);
+    } else {
+        fs::read(path).ok()?.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+impl ConfigWatcher {
+    /// Creates a watcher and performs the initial load.
+    ///
+    /// The initial configuration must load and validate successfully. If the
+    /// provider finds no configuration at all, the watcher starts from
+    /// `AppConfig::default()` and picks up the files once they appear.
+    pub fn new(provider: impl ConfigurationProvider + 'static) -> Result<Self, ConfigError> {
+        let fingerprints = provider
+            .watch_paths()
+            .into_iter()
+            .map(|path| {
+                let print = fingerprint(&path);
+                (path, print)
+            })
+            .collect();
+        let config = provider.load()?.unwrap_or_default();
+        provider.validate(&config)?;
+
+        Ok(Self {
+            state: Arc::new(WatcherState {
+                provider: Box::new(provider),
+                current: RwLock::new(config),
+                reload: Mutex::new(()),
+                fingerprints: Mutex::new(fingerprints),
+                subscribers: Mutex::new(Vec::new()),
+            }),
+        })
+    }
+
+    /// Returns the last configuration that loaded and validated successfully.
+    pub fn current(&self) -> AppConfig {
+        self.state.current.read().unwrap().clone()
+    }
+
+    /// Subscribes to configuration updates.
+    ///
+    /// Each successful reload that changes at least one field is sent to
+    /// every subscriber. Dropping the receiver unsubscribes.
+    pub fn subscribe(&self) -> Receiver<ConfigUpdate> {
+        let (sender, receiver) = mpsc::channel();
+        self.state.subscribers.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// Checks the watched files once and reloads if any of them changed.
+    ///
+    /// Returns the published update, `Ok(None)` if nothing changed, or the
+    /// error that caused the reload to be rejected.
+    pub fn check_for_changes(&self) -> Result<Option<ConfigUpdate>, ConfigError> {
+        let mut fingerprints = self.state.fingerprints.lock().unwrap();
+        let mut modified = false;
+        for path in self.state.provider.watch_paths() {
+            let print = fingerprint(&path);
+            if fingerprints.get(&path) != Some(&print) {
+                fingerprints.insert(path, print);
+                modified = true;
+            }
+        }
+
+        if modified {
+            self.reload()
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reloads the configuration unconditionally.
+    ///
+    /// The new configuration is published only if it validates and differs
+    /// from the current one.
+    pub fn reload(&self) -> Result<Option<ConfigUpdate>, ConfigError> {
+        // Without this, a slower reload could replace a newer configuration
+        // with the stale one it loaded earlier.
+        let _reload = self.state.reload.lock().unwrap();
+        let config = self.state.provider.load()?.unwrap_or_default();
+        self.state.provider.validate(&config)?;
+
+        let mut current = self.state.current.write().unwrap();
+        let changes = diff_configs(&current, &config);
+        if changes.is_empty() {
+            return Ok(None);
+        }
+        *current = config.clone();
+        drop(current);
+
+        let update = ConfigUpdate { config, changes };
+        self.state
+            .subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(update.clone()).is_ok());
+        Ok(Some(update))
+    }
+
+    /// Starts polling the watched files on a background thread.
+    ///
+    /// Failed reloads are logged and otherwise ignored, so that a broken
+    /// edit never replaces a working configuration.
+    pub fn watch(&self, interval: Duration) -> WatchHandle {
+        let stop = Arc::new(AtomicBool::new(false));
+        let watcher = self.clone();
+        let thread_stop = stop.clone();
+
+        let thread = thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if let Err(e) = watcher.check_for_changes() {
+                    tracing::warn!(
+                        error = %e,
+                        "Configuration reload rejected; keeping the last good configuration"
+                    );
+                }
+                thread::sleep(interval);
+            }
+        });
+
+        WatchHandle {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        layers::LayeredConfigurationProvider, loader::FileConfigurationProvider, types::LogLevel,
+    };
+    use tempfile::NamedTempFile;
+    use test_utils::mock_fs::create_temp_config_file;
+
+    fn rewrite(file: &NamedTempFile, content: &str) {
+        fs::write(file.path(), content).unwrap();
+    }
+
+    #[test]
+    fn test_initial_load() {
+        let file = create_temp_config_file(r#"log_level = "info""#);
+        let watcher = ConfigWatcher::new(FileConfigurationProvider::new(file.path())).unwrap();
+        assert_eq!(watcher.current().log_level, Some(LogLevel::Info));
+    }
+
+    #[test]
+    fn test_publishes_changes_to_subscribers() {
+        let file = create_temp_config_file(
+            r#"
+            log_level = "info"
+            [feature_flags]
+            new_ui = false
+            "#,
+        );
+        let watcher = ConfigWatcher::new(FileConfigurationProvider::new(file.path())).unwrap();
+        let updates = watcher.subscribe();
+
+        rewrite(
+            &file,
+            r#"
+            log_level = "debug"
+            [feature_flags]
+            new_ui = true
+            "#,
+        );
+        let update = watcher.check_for_changes().unwrap().unwrap();
+
+        assert_eq!(update.config.log_level, Some(LogLevel::Debug));
+        let fields: Vec<&str> = update.changes.iter().map(ConfigChange::field).collect();
+        assert_eq!(fields, vec!["log_level", "feature_flags.new_ui"]);
+
+        let received = updates.try_recv().unwrap();
+        assert_eq!(received.config, update.config);
+        assert_eq!(watcher.current().log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_no_update_without_changes() {
+        let file = create_temp_config_file(r#"log_level = "info""#);
+        let watcher = ConfigWatcher::new(FileConfigurationProvider::new(file.path())).unwrap();
+        let updates = watcher.subscribe();
+
+        assert!(watcher.check_for_changes().unwrap().is_none());
+
+        // Touching the file without changing any value is not an update.
+        rewrite(&file, "log_level = \"info\"\n");
+        assert!(watcher.check_for_changes().unwrap().is_none());
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_keeps_last_good_config_on_invalid_reload() {
+        let file = create_temp_config_file(
+            r#"
+            environment = "production"
+            log_level = "warn"
+            "#,
+        );
+        let watcher = ConfigWatcher::new(FileConfigurationProvider::new(file.path())).unwrap();
+        let updates = watcher.subscribe();
+
+        rewrite(&file, r#"environment = """#);
+        let result = watcher.check_for_changes();
+        assert!(result.is_err());
+
+        rewrite(&file, "this is not valid toml");
+        assert!(matches!(
+            watcher.check_for_changes(),
+            Err(ConfigError::File { source, .. }) if matches!(*source, ConfigError::Toml(_))
+        ));
+
+        assert!(updates.try_recv().is_err());
+        assert_eq!(
+            watcher.current().environment,
+            Some("production".to_string())
+        );
+        assert_eq!(watcher.current().log_level, Some(LogLevel::Warn));
+    }
+
+    #[test]
+    fn test_watches_every_layer() {
+        let base = create_temp_config_file(r#"log_level = "info""#);
+        let overrides = create_temp_config_file(r#"environment = "staging""#);
+        let provider = LayeredConfigurationProvider::new()
+            .with_provider(Box::new(FileConfigurationProvider::new(base.path())))
+            .with_provider(Box::new(FileConfigurationProvider::new(overrides.path())));
+        let watcher = ConfigWatcher::new(provider).unwrap();
+
+        rewrite(&base, r#"log_level = "error""#);
+        let update = watcher.check_for_changes().unwrap().unwrap();
+        assert_eq!(update.config.log_level, Some(LogLevel::Error));
+        assert_eq!(update.config.environment, Some("staging".to_string()));
+    }
+
+    /// Numbers its loads, and takes a while over the first reload.
+    struct SlowFirstReload {
+        loads: std::sync::atomic::AtomicUsize,
+    }
+
+    impl ConfigurationProvider for SlowFirstReload {
+        fn load(&self) -> Result<Option<AppConfig>, ConfigError> {
+            let load = self.loads.fetch_add(1, Ordering::SeqCst);
+            if load == 1 {
+                thread::sleep(Duration::from_millis(200));
+            }
+            Ok(Some(AppConfig {
+                environment: Some(format!("load-{}", load)),
+                ..AppConfig::default()
+            }))
+        }
+
+        fn validate(&self, _config: &AppConfig) -> Result<(), ConfigError> {
+            Ok(())
+        }
+
+        fn name(&self) -> String {
+            "slow".to_string()
+        }
+    }
+
+    #[test]
+    fn test_concurrent_reloads_publish_in_order() {
+        let watcher = ConfigWatcher::new(SlowFirstReload {
+            loads: Default::default(),
+        })
+        .unwrap();
+
+        let slow = {
+            let watcher = watcher.clone();
+            thread::spawn(move || watcher.reload().unwrap())
+        };
+        thread::sleep(Duration::from_millis(50));
+        watcher.reload().unwrap();
+        slow.join().unwrap();
+
+        assert_eq!(watcher.current().environment, Some("load-2".to_string()));
+    }
+
+    #[test]
+    fn test_background_watch() {
+        let file = create_temp_config_file(r#"log_level = "info""#);
+        let watcher = ConfigWatcher::new(FileConfigurationProvider::new(file.path())).unwrap();
+        let updates = watcher.subscribe();
+        let _handle = watcher.watch(Duration::from_millis(10));
+
+        rewrite(&file, r#"log_level = "trace""#);
+        let update = updates.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(update.config.log_level, Some(LogLevel::Trace));
+    }
+
+    #[test]
+    fn test_reloads_when_directory_files_change() {
+        use crate::directory::DirectoryConfigurationProvider;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("10-base.toml"), "log_level = \"info\"\n").unwrap();
+        let watcher = ConfigWatcher::new(DirectoryConfigurationProvider::new(dir.path())).unwrap();
+
+        let local = dir.path().join("20-local.toml");
+        fs::write(&local, "log_level = \"debug\"\n").unwrap();
+        let update = watcher.check_for_changes().unwrap().unwrap();
+        assert_eq!(update.config.log_level, Some(LogLevel::Debug));
+
+        fs::remove_file(&local).unwrap();
+        let update = watcher.check_for_changes().unwrap().unwrap();
+        assert_eq!(update.config.log_level, Some(LogLevel::Info));
+    }
+}
@@ -0,0 +1,339 @@
+use crate::{
+    errors::ConfigError,
+    migration::{Migrated, MigrationRegistry},
+    render::find_mut,
+    types::AppConfig,
+};
+use std::{
+    fs::{self, Permissions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use toml_edit::{DocumentMut, Item, Table, Value};
+use uuid::Uuid;
+
+/// Edits individual keys of a TOML configuration file in place.
+///
+/// Unlike rendering a whole `AppConfig`, this keeps comments, ordering and
+/// formatting of everything that is not changed.
+pub struct ConfigWriter {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl ConfigWriter {
+    /// Reads the file at `path`. A missing file is treated as empty and is
+    /// created by `save`.
+    ///
+    /// The file only needs to be valid TOML, so that a configuration that
+    /// no longer loads can be repaired; `save` checks the result.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref().to_path_buf();
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let document = DocumentMut::from_str(&contents)
+            .map_err(|e| ConfigError::Toml(serde::de::Error::custom(e)))?;
+        Ok(Self { path, document })
+    }
+
+    /// Sets the dotted `field` to `value`, creating tables as needed.
+    ///
+    /// `value` is read as a TOML value if it is one (`true`, `3`,
+    /// `["a", "b"]`) and as a string otherwise, so `debug` and `"debug"`
+    /// are equivalent.
+    pub fn set(&mut self, field: &str, value: &str) -> Result<(), ConfigError> {
+        let value = Value::from_str(value).unwrap_or_else(|_| Value::from(value));
+        let (parents, key) = match field.rsplit_once('.') {
+            Some((parents, key)) => (Some(parents), key),
+            None => (None, field),
+        };
+
+        let mut table = self.document.as_table_mut() as &mut dyn toml_edit::TableLike;
+        for segment in parents.into_iter().flat_map(|parents| parents.split('.')) {
+            let item = table.entry(segment).or_insert_with(|| {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                Item::Table(table)
+            });
+            table = item
+                .as_table_like_mut()
+                .ok_or_else(|| not_a_table(field, segment))?;
+        }
+
+        match table.get_mut(key) {
+            // Assigning through the existing value keeps its comments.
+            Some(Item::Value(existing)) => {
+                let decor = existing.decor().clone();
+                *existing = value;
+                *existing.decor_mut() = decor;
+            }
+            _ => {
+                table.insert(key, Item::Value(value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the dotted `field`, returning whether it was set.
+    pub fn remove(&mut self, field: &str) -> bool {
+        let (table, key) = match field.rsplit_once('.') {
+            Some((parents, key)) => {
+                match find_mut(self.document.as_table_mut(), parents)
+                    .and_then(Item::as_table_like_mut)
+                {
+                    Some(table) => (table, key),
+                    None => return false,
+                }
+            }
+            None => (
+                self.document.as_table_mut() as &mut dyn toml_edit::TableLike,
+                field,
+            ),
+        };
+        table.remove(key).is_some()
+    }
+
+    /// Upgrades the document to the latest `config_version`, returning
+    /// `None` if it is already up to date.
+    pub fn migrate(
+        &mut self,
+        migrations: &MigrationRegistry,
+    ) -> Result<Option<Migrated>, ConfigError> {
+        migrations.migrate_toml(&mut self.document)
+    }
+
+    /// Returns the document as it would be written by `save`.
+    pub fn contents(&self) -> String {
+        self.document.to_string()
+    }
+
+    /// Checks that the edited document is still a valid configuration and
+    /// writes it back to the file.
+    ///
+    /// The file is replaced atomically, so a concurrent reader such as a
+    /// `ConfigWatcher` never sees a partially written file. If the path is a
+    /// symlink, the file it points to is replaced and the link is kept. The
+    /// new file keeps the permissions of the old one.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let contents = self.contents();
+        toml::from_str::<AppConfig>(&contents)?;
+
+        let target = match fs::canonicalize(&self.path) {
+            Ok(target) => target,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.path.clone(),
+            Err(e) => return Err(e.into()),
+        };
+        let permissions = match fs::metadata(&target) {
+            Ok(metadata) => Some(metadata.permissions()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let file_name = target.file_name().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{} is not a file", target.display()),
+            )
+        })?;
+
+        // The temporary file must be on the same file system for the rename
+        // to be atomic, so it goes next to the target.
+        let temp = target.with_file_name(format!(
+            ".{}.{}.tmp",
+            file_name.to_string_lossy(),
+            Uuid::new_v4().simple()
+        ));
+        let result =
+            write_new(&temp, &contents, permissions).and_then(|()| fs::rename(&temp, &target));
+        if result.is_err() {
+            let _ = fs::remove_file(&temp);
+        }
+        Ok(result?)
+    }
+}
+
+/// Writes `contents` to a file that must not exist yet and flushes it to
+/// disk.
+fn write_new(path: &Path, contents: &str, permissions: Option<Permissions>) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)?;
+    if let Some(permissions) = permissions {
+        file.set_permissions(permissions)?;
+    }
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()
+}
+
+fn not_a_table(field: &str, segment: &str) -> ConfigError {
+    ConfigError::Serialization(format!(
+        "cannot set `{}`: `{}` is not a table",
+        field, segment
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        loader::FileConfigurationProvider, traits::ConfigurationProvider, types::LogLevel,
+    };
+    use test_utils::mock_fs::create_temp_config_file;
+
+    #[test]
+    fn test_set_preserves_comments() {
+        let file = create_temp_config_file(
+            "# Ciphr configuration\n\
+             environment = \"development\"\n\
+             log_level = \"info\" # verbose enough\n\
+             \n\
+             # Flags under test\n\
+             [feature_flags]\n\
+             new_ui = false\n",
+        );
+
+        let mut writer = ConfigWriter::open(file.path()).unwrap();
+        writer.set("log_level", "debug").unwrap();
+        writer.set("feature_flags.new_ui", "true").unwrap();
+        writer.set("monitoring.http.port", "9090").unwrap();
+        writer.save().unwrap();
+
+        let contents = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(
+            contents,
+            "# Ciphr configuration\n\
+             environment = \"development\"\n\
+             log_level = \"debug\" # verbose enough\n\
+             \n\
+             # Flags under test\n\
+             [feature_flags]\n\
+             new_ui = true\n\
+             \n\
+             [monitoring.http]\n\
+             port = 9090\n"
+        );
+
+        let config = FileConfigurationProvider::new(file.path())
+            .load()
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.log_level, Some(LogLevel::Debug));
+    }
+
+    #[test]
+    fn test_save_rejects_invalid_config() {
+        let file = create_temp_config_file("log_level = \"info\"\n");
+
+        let mut writer = ConfigWriter::open(file.path()).unwrap();
+        writer.set("log_level", "verbose").unwrap();
+        assert!(matches!(writer.save(), Err(ConfigError::Toml(_))));
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "log_level = \"info\"\n"
+        );
+    }
+
+    #[test]
+    fn test_open_only_requires_valid_toml() {
+        let file = create_temp_config_file("log_level = \"verbose\"\n");
+
+        let mut writer = ConfigWriter::open(file.path()).unwrap();
+        writer.set("log_level", "debug").unwrap();
+        writer.save().unwrap();
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "log_level = \"debug\"\n"
+        );
+
+        let file = create_temp_config_file("log_level = \n");
+        assert!(matches!(
+            ConfigWriter::open(file.path()),
+            Err(ConfigError::Toml(_))
+        ));
+    }
+
+    #[test]
+    fn test_save_leaves_no_temporary_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ciphr.toml");
+
+        let mut writer = ConfigWriter::open(&path).unwrap();
+        writer.set("log_level", "debug").unwrap();
+        writer.save().unwrap();
+        writer.set("log_level", "verbose").unwrap();
+        assert!(writer.save().is_err());
+
+        let entries: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(entries, ["ciphr.toml"]);
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "log_level = \"debug\"\n"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_keeps_permissions_and_symlinks() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("shared.toml");
+        let link = dir.path().join("ciphr.toml");
+        fs::write(&target, "log_level = \"info\"\n").unwrap();
+        fs::set_permissions(&target, Permissions::from_mode(0o640)).unwrap();
+        symlink(&target, &link).unwrap();
+
+        let mut writer = ConfigWriter::open(&link).unwrap();
+        writer.set("log_level", "debug").unwrap();
+        writer.save().unwrap();
+
+        assert!(fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(
+            fs::read_to_string(&target).unwrap(),
+            "log_level = \"debug\"\n"
+        );
+        let mode = fs::metadata(&target).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn test_remove() {
+        let file = create_temp_config_file("log_level = \"info\"\n[feature_flags]\nbeta = true\n");
+
+        let mut writer = ConfigWriter::open(file.path()).unwrap();
+        assert!(writer.remove("feature_flags.beta"));
+        assert!(!writer.remove("feature_flags.missing"));
+        assert_eq!(writer.contents(), "log_level = \"info\"\n[feature_flags]\n");
+    }
+
+    #[test]
+    fn test_migrate() {
+        use crate::migration::Migration;
+
+        let file = create_temp_config_file("# Logging\nlog = \"debug\"\n");
+        let migrations = MigrationRegistry::new()
+            .with_migration(Migration::new(1, "rename `log`").rename("log", "log_level"));
+
+        let mut writer = ConfigWriter::open(file.path()).unwrap();
+        let migrated = writer.migrate(&migrations).unwrap().unwrap();
+        assert_eq!((migrated.from, migrated.to), (1, 2));
+        writer.save().unwrap();
+
+        assert_eq!(
+            fs::read_to_string(file.path()).unwrap(),
+            "# Logging\nlog_level = \"debug\"\nconfig_version = 2\n"
+        );
+        let mut writer = ConfigWriter::open(file.path()).unwrap();
+        assert_eq!(writer.migrate(&migrations).unwrap(), None);
+    }
+}
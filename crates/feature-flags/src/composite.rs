@@ -0,0 +1,175 @@
+//! Chains several evaluation strategies with explicit precedence.
+
+use crate::{
+    evaluator::{EvaluationContext, FeatureFlagEvaluator},
+    rules::RuleError,
+    strategies::{
+        KillSwitchEvaluator, PercentageRolloutEvaluator, StaticEvaluator, TargetingEvaluator,
+    },
+};
+use config::flag::FlagDefinition;
+use std::collections::HashMap;
+
+/// The outcome of evaluating a flag with a `CompositeEvaluator`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Evaluation {
+    /// Whether the flag is enabled.
+    pub enabled: bool,
+    /// The name of the strategy that decided, or `None` if none did and the
+    /// flag is off by default.
+    pub decided_by: Option<String>,
+}
+
+/// An evaluator that asks its strategies in order and takes the first
+/// decision.
+///
+/// Each strategy either decides a flag or leaves it to the next one (see
+/// `FeatureFlagEvaluator::decide`), so a chain can say "a kill switch wins,
+/// then the allowlist, then the segments, then the rollout, then the
+/// default":
+///
+/// ```
+/// use feature_flags::{
+///     composite::CompositeEvaluator,
+///     evaluator::EvaluationContext,
+///     strategies::{AllowlistEvaluator, KillSwitchEvaluator, PercentageRolloutEvaluator},
+/// };
+/// use std::collections::{HashMap, HashSet};
+///
+/// let evaluator = CompositeEvaluator::new()
+///     .with_strategy("kill-switch", KillSwitchEvaluator::new(HashSet::new()))
+///     .with_strategy(
+///         "allowlist",
+///         AllowlistEvaluator::new(HashMap::from([(
+///             "new_ui".to_string(),
+///             HashSet::from(["alice".to_string()]),
+///         )])),
+///     )
+///     .with_strategy(
+///         "rollout",
+///         PercentageRolloutEvaluator::new(HashMap::from([("new_ui".to_string(), 0.0)])),
+///     );
+///
+/// let alice = EvaluationContext {
+///     user_id: Some("alice".to_string()),
+///     ..Default::default()
+/// };
+/// let evaluation = evaluator.evaluate("new_ui", &alice);
+/// assert!(evaluation.enabled);
+/// assert_eq!(evaluation.decided_by.as_deref(), Some("allowlist"));
+/// ```
+///
+/// A flag no strategy decides is off.
+#[derive(Default)]
+pub struct CompositeEvaluator {
+    strategies: Vec<(String, Box<dyn FeatureFlagEvaluator + Send + Sync>)>,
+    selections: HashMap<String, Vec<String>>,
+}
+
+impl CompositeEvaluator {
+    /// Creates an evaluator without strategies, which leaves every flag off.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Builds the standard chain from flag definitions, such as
+    /// `AppConfig::feature_flags`:
+    ///
+    /// 1. `kill-switch` turns disabled flags off;
+    /// 2. `targeting` turns a flag off for users outside its `segments` or
+    ///    not matching its `rule`;
+    /// 3. `rollout` decides flags with a `rollout` by percentage, among the
+    ///    users the targeting let through;
+    /// 4. `default` turns every flag that got this far on.
+    ///
+    /// Only the kill switch looks at `enabled`; the other strategies cover
+    /// every flag, so the chain without it (see `without_strategy`) still
+    /// evaluates disabled flags as if they were enabled.
+    ///
+    /// Fails if a flag's rule does not compile.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Result<Self, RuleError> {
+        let rollouts = flags
+            .iter()
+            .filter_map(|(name, flag)| Some((name.clone(), flag.rollout? as f32)))
+            .collect();
+        let defaults = flags.keys().map(|name| (name.clone(), true)).collect();
+        Ok(Self::new()
+            .with_strategy("kill-switch", KillSwitchEvaluator::from_flags(flags))
+            .with_strategy("targeting", TargetingEvaluator::from_flags(flags)?)
+            .with_strategy("rollout", PercentageRolloutEvaluator::new(rollouts))
+            .with_strategy("default", StaticEvaluator::new(defaults)))
+    }
+
+    /// Adds a strategy after the existing ones. `name` is reported in
+    /// `Evaluation::decided_by` and used by `with_flag_strategies`.
+    pub fn with_strategy(
+        mut self,
+        name: impl Into<String>,
+        evaluator: impl FeatureFlagEvaluator + Send + Sync + 'static,
+    ) -> Self {
+        self.strategies.push((name.into(), Box::new(evaluator)));
+        self
+    }
+
+    /// Removes the strategy with the given name, if added.
+    pub fn without_strategy(mut self, name: &str) -> Self {
+        self.strategies.retain(|(existing, _)| existing != name);
+        self
+    }
+
+    /// Limits `flag` to the named strategies. They are still asked in the
+    /// order they were added; names of strategies that were never added
+    /// are ignored.
+    pub fn with_flag_strategies<I, S>(mut self, flag: impl Into<String>, strategies: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let strategies = strategies.into_iter().map(Into::into).collect();
+        self.selections.insert(flag.into(), strategies);
+        self
+    }
+
+    /// Returns the names of the strategies in the order they are asked.
+    pub fn strategy_names(&self) -> Vec<&str> {
+        self.strategies
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    /// Evaluates `flag_name` and reports which strategy decided it.
+    pub fn evaluate(&self, flag_name: &str, context: &EvaluationContext) -> Evaluation {
+        let selection = self.selections.get(flag_name);
+        self.strategies
+            .iter()
+            .filter(|(name, _)| match selection {
+                Some(selected) => selected.contains(name),
+                None => true,
+            })
+            .find_map(|(name, evaluator)| {
+                let enabled = evaluator.decide(flag_name, context)?;
+                Some(Evaluation {
+                    enabled,
+                    decided_by: Some(name.clone()),
+                })
+            })
+            .unwrap_or(Evaluation {
+                enabled: false,
+                decided_by: None,
+            })
+    }
+}
+
+impl FeatureFlagEvaluator for CompositeEvaluator {
+    fn is_enabled(&self, flag_name: &str, context: &EvaluationContext) -> bool {
+        self.evaluate(flag_name, context).enabled
+    }
+
+    /// Decides a flag if one of the strategies does, so that composites
+    /// can be nested.
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        let evaluation = self.evaluate(flag_name, context);
+        evaluation.decided_by.map(|_| evaluation.enabled)
+    }
+}
@@ -11,6 +11,24 @@ pub struct EvaluationContext {
     pub properties: HashMap<String, String>,
 }
 
+impl EvaluationContext {
+    /// Returns the value of an attribute used by targeting rules.
+    ///
+    /// Attributes are looked up in `properties`; `user_id` and
+    /// `user_segment` are available under those names unless a property
+    /// shadows them.
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        if let Some(value) = self.properties.get(name) {
+            return Some(value);
+        }
+        match name {
+            "user_id" => self.user_id.as_deref(),
+            "user_segment" => self.user_segment.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 /// A trait for evaluating the state of a feature flag.
 ///
 /// This trait allows for different strategies to be used for flag evaluation,
@@ -25,4 +43,13 @@ pub trait FeatureFlagEvaluator {
     /// # Returns
     /// `true` if the feature should be considered enabled, `false` otherwise.
     fn is_enabled(&self, flag_name: &str, context: &EvaluationContext) -> bool;
+
+    /// Decides the flag as one strategy in a `CompositeEvaluator`.
+    ///
+    /// Returns `None` to leave the decision to the next strategy, e.g. when
+    /// the flag is not configured for this one or the user is not targeted
+    /// by it. Defaults to deciding every flag with `is_enabled`.
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        Some(self.is_enabled(flag_name, context))
+    }
 } 
\ No newline at end of file
@@ -1,6 +1,9 @@
+pub mod composite;
 pub mod evaluator;
 pub mod manager;
+pub mod rules;
 pub mod strategies;
+pub mod variants;
 
 #[cfg(test)]
 mod tests {
@@ -1,11 +1,32 @@
+use crate::composite::CompositeEvaluator;
 use crate::evaluator::{EvaluationContext, FeatureFlagEvaluator};
+use crate::rules::RuleError;
+use crate::variants::VariantEvaluator;
+use config::types::AppConfig;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 
 /// Manages the state and evaluation of feature flags.
+///
+/// A flag is resolved in three steps:
+///
+/// 1. an override set with `set_override` wins outright;
+/// 2. otherwise a flag whose stored state is `false` is off;
+/// 3. otherwise the evaluator decides, also for flags with no stored state.
+///
+/// The stored state is the flag's master switch, so `update_flag(name,
+/// false)` turns a flag off at runtime and `update_flag(name, true)` hands
+/// it back to the evaluator. Overrides force a flag on or off for everyone,
+/// e.g. while investigating an incident, until they are cleared.
+///
+/// Multivariate flags are read with `get_variant`, which assigns a variant
+/// only to users the flag is enabled for.
 pub struct FeatureFlagManager<E: FeatureFlagEvaluator> {
     evaluator: E,
+    variants: VariantEvaluator,
     flags: Arc<RwLock<HashMap<String, bool>>>,
+    overrides: Arc<RwLock<HashMap<String, bool>>>,
 }
 
 impl<E: FeatureFlagEvaluator> FeatureFlagManager<E> {
@@ -17,22 +38,96 @@ impl<E: FeatureFlagEvaluator> FeatureFlagManager<E> {
     pub fn new(evaluator: E, flags: HashMap<String, bool>) -> Self {
         Self {
             evaluator,
+            variants: VariantEvaluator::new(HashMap::new()),
             flags: Arc::new(RwLock::new(flags)),
+            overrides: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    /// Checks if a feature is enabled.
-    ///
-    /// This method delegates the evaluation to the configured evaluator.
+    /// Checks if a feature is enabled, following the order described on
+    /// `FeatureFlagManager`.
     pub fn is_enabled(&self, flag_name: &str, context: &EvaluationContext) -> bool {
+        if let Some(&enabled) = self.overrides.read().unwrap().get(flag_name) {
+            return enabled;
+        }
+        if self.flags.read().unwrap().get(flag_name) == Some(&false) {
+            return false;
+        }
         self.evaluator.is_enabled(flag_name, context)
     }
 
-    /// A simple method to update a flag's state at runtime.
-    /// In a real application, this would likely involve a more complex
-    /// mechanism, such as a background thread polling a remote service.
+    /// Sets the variants of multivariate flags. There are none by default.
+    pub fn with_variants(mut self, variants: VariantEvaluator) -> Self {
+        self.variants = variants;
+        self
+    }
+
+    /// Returns the variant of a multivariate flag assigned to the user, as a
+    /// `T` such as a `String`, a number or a type deserializing a table.
+    ///
+    /// Returns `None` if the flag is off for the user, has no variants, or
+    /// the assigned value is not a `T`.
+    pub fn get_variant<T: DeserializeOwned>(
+        &self,
+        flag_name: &str,
+        context: &EvaluationContext,
+    ) -> Option<T> {
+        if !self.is_enabled(flag_name, context) {
+            return None;
+        }
+        self.variants.get_variant(flag_name, context)
+    }
+
+    /// Updates a flag's stored state at runtime.
+    ///
+    /// Disabling a flag turns it off for everyone; enabling it leaves the
+    /// decision to the evaluator again.
     pub fn update_flag(&self, flag_name: String, enabled: bool) {
         let mut flags = self.flags.write().unwrap();
         flags.insert(flag_name, enabled);
     }
+
+    /// Returns a flag's stored state, if it has one.
+    pub fn flag_state(&self, flag_name: &str) -> Option<bool> {
+        self.flags.read().unwrap().get(flag_name).copied()
+    }
+
+    /// Forces a flag on or off for everyone, regardless of its stored state
+    /// and the evaluator.
+    pub fn set_override(&self, flag_name: impl Into<String>, enabled: bool) {
+        let mut overrides = self.overrides.write().unwrap();
+        overrides.insert(flag_name.into(), enabled);
+    }
+
+    /// Removes an override, returning the value it forced.
+    pub fn clear_override(&self, flag_name: &str) -> Option<bool> {
+        self.overrides.write().unwrap().remove(flag_name)
+    }
+
+    /// Returns the active overrides.
+    pub fn overrides(&self) -> HashMap<String, bool> {
+        self.overrides.read().unwrap().clone()
+    }
+}
+
+impl FeatureFlagManager<CompositeEvaluator> {
+    /// Creates a manager for the flags in `config.feature_flags`.
+    ///
+    /// Each flag's stored state is its `enabled` setting, the evaluator is
+    /// the standard chain from `CompositeEvaluator::from_flags` and the
+    /// variants are the flags' `variants`. The chain's `kill-switch` is
+    /// left out, so that the stored state is the only master switch and
+    /// `update_flag(name, true)` also turns on flags disabled in `config`.
+    /// Fails if a flag's rule does not compile.
+    pub fn from_config(config: &AppConfig) -> Result<Self, RuleError> {
+        let evaluator =
+            CompositeEvaluator::from_flags(&config.feature_flags)?.without_strategy("kill-switch");
+        let flags = config
+            .feature_flags
+            .iter()
+            .map(|(name, flag)| (name.clone(), flag.is_enabled()))
+            .collect();
+        Ok(Self::new(evaluator, flags)
+            .with_variants(VariantEvaluator::from_flags(&config.feature_flags)))
+    }
 } 
\ No newline at end of file
@@ -0,0 +1,352 @@
+//! Targeting on arbitrary attributes of the evaluation context.
+//!
+//! Flags describe who they are for with a `rule` in their configuration,
+//! see `config::rule`. A `RuleEvaluator` compiles those rules once and
+//! checks them against `EvaluationContext::attribute`.
+
+use crate::evaluator::{EvaluationContext, FeatureFlagEvaluator};
+use chrono::{DateTime, NaiveDate, Utc};
+use config::{
+    flag::FlagDefinition,
+    rule::{Condition, Operator, Rule},
+};
+use regex::Regex;
+use semver::{Version, VersionReq};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Errors raised when compiling a targeting rule.
+#[derive(Debug, Error)]
+pub enum RuleError {
+    #[error("invalid regular expression `{pattern}`: {source}")]
+    Regex {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+
+    #[error("invalid version requirement `{requirement}`: {source}")]
+    Version {
+        requirement: String,
+        #[source]
+        source: semver::Error,
+    },
+
+    #[error("invalid date `{0}`, expected e.g. `2025-01-01` or `2025-01-01T09:00:00Z`")]
+    Date(String),
+
+    #[error("invalid range: `min` ({min}) is greater than `max` ({max})")]
+    Range { min: f64, max: f64 },
+
+    #[error("invalid range bound {0}, expected a finite number")]
+    RangeBound(f64),
+
+    #[error("flag `{flag}`: {source}")]
+    Flag {
+        flag: String,
+        #[source]
+        source: Box<RuleError>,
+    },
+}
+
+/// A rule with its patterns, requirements and dates parsed.
+#[derive(Debug)]
+enum CompiledRule {
+    All(Vec<CompiledRule>),
+    Any(Vec<CompiledRule>),
+    Condition { attribute: String, test: Test },
+}
+
+#[derive(Debug)]
+enum Test {
+    Equals(String),
+    In(Vec<String>),
+    Matches(Regex),
+    Version(VersionReq),
+    Range { min: Option<f64>, max: Option<f64> },
+    After(DateTime<Utc>),
+}
+
+impl CompiledRule {
+    fn compile(rule: &Rule) -> Result<Self, RuleError> {
+        let compile_all =
+            |rules: &[Rule]| rules.iter().map(Self::compile).collect::<Result<_, _>>();
+        Ok(match rule {
+            Rule::All(rules) => CompiledRule::All(compile_all(rules)?),
+            Rule::Any(rules) => CompiledRule::Any(compile_all(rules)?),
+            Rule::Condition(Condition {
+                attribute,
+                operator,
+            }) => CompiledRule::Condition {
+                attribute: attribute.clone(),
+                test: Test::compile(operator)?,
+            },
+        })
+    }
+
+    fn matches(&self, context: &EvaluationContext) -> bool {
+        match self {
+            CompiledRule::All(rules) => rules.iter().all(|rule| rule.matches(context)),
+            CompiledRule::Any(rules) => rules.iter().any(|rule| rule.matches(context)),
+            CompiledRule::Condition { attribute, test } => context
+                .attribute(attribute)
+                .is_some_and(|value| test.matches(value)),
+        }
+    }
+}
+
+impl Test {
+    fn compile(operator: &Operator) -> Result<Self, RuleError> {
+        Ok(match operator {
+            Operator::Equals(value) => Test::Equals(value.clone()),
+            Operator::In(values) => Test::In(values.clone()),
+            Operator::Matches(pattern) => {
+                Test::Matches(Regex::new(pattern).map_err(|source| RuleError::Regex {
+                    pattern: pattern.clone(),
+                    source,
+                })?)
+            }
+            Operator::Version(requirement) => {
+                Test::Version(VersionReq::parse(requirement).map_err(|source| {
+                    RuleError::Version {
+                        requirement: requirement.clone(),
+                        source,
+                    }
+                })?)
+            }
+            Operator::Range { min, max } => {
+                if let Some(bound) = min.iter().chain(max).find(|bound| !bound.is_finite()) {
+                    return Err(RuleError::RangeBound(*bound));
+                }
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        return Err(RuleError::Range {
+                            min: *min,
+                            max: *max,
+                        });
+                    }
+                }
+                Test::Range {
+                    min: *min,
+                    max: *max,
+                }
+            }
+            Operator::After(date) => {
+                Test::After(parse_date(date).ok_or_else(|| RuleError::Date(date.clone()))?)
+            }
+        })
+    }
+
+    /// Checks an attribute value. Values that cannot be read as a number,
+    /// version or date never match the corresponding test, and neither do
+    /// `NaN` and infinite numbers.
+    fn matches(&self, value: &str) -> bool {
+        match self {
+            Test::Equals(expected) => value == expected,
+            Test::In(values) => values.iter().any(|expected| expected == value),
+            Test::Matches(regex) => regex.is_match(value),
+            Test::Version(requirement) => {
+                Version::parse(value).is_ok_and(|version| requirement.matches(&version))
+            }
+            Test::Range { min, max } => value.trim().parse::<f64>().is_ok_and(|number| {
+                number.is_finite()
+                    && !min.is_some_and(|min| number < min)
+                    && !max.is_some_and(|max| number > max)
+            }),
+            Test::After(date) => parse_date(value).is_some_and(|value| value > *date),
+        }
+    }
+}
+
+/// Reads an RFC 3339 timestamp, or a plain date as midnight UTC.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc())
+}
+
+/// An evaluator that enables features for users whose attributes match the
+/// flag's targeting rule.
+///
+/// `all` with no rules matches everyone and `any` with no rules nobody. A
+/// condition on an attribute the context does not have never matches.
+pub struct RuleEvaluator {
+    rules: HashMap<String, CompiledRule>,
+}
+
+impl RuleEvaluator {
+    /// Compiles the rule of each flag.
+    ///
+    /// Fails with `RuleError::Flag` if a rule has an invalid regular
+    /// expression, version requirement, range or date.
+    pub fn new(rules: HashMap<String, Rule>) -> Result<Self, RuleError> {
+        let rules = rules
+            .into_iter()
+            .map(|(flag, rule)| {
+                let compiled = compile(&flag, &rule)?;
+                Ok((flag, compiled))
+            })
+            .collect::<Result<_, RuleError>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Builds the rules from flag definitions, such as
+    /// `AppConfig::feature_flags`. Only enabled flags that have a `rule`
+    /// are included.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Result<Self, RuleError> {
+        let rules = flags
+            .iter()
+            .filter(|(_, flag)| flag.is_enabled())
+            .filter_map(|(name, flag)| Some((name.clone(), flag.rule.clone()?)))
+            .collect();
+        Self::new(rules)
+    }
+}
+
+fn compile(flag: &str, rule: &Rule) -> Result<CompiledRule, RuleError> {
+    CompiledRule::compile(rule).map_err(|source| RuleError::Flag {
+        flag: flag.to_string(),
+        source: Box::new(source),
+    })
+}
+
+impl FeatureFlagEvaluator for RuleEvaluator {
+    fn is_enabled(&self, flag_name: &str, context: &EvaluationContext) -> bool {
+        self.rules
+            .get(flag_name)
+            .is_some_and(|rule| rule.matches(context))
+    }
+    /// Enables the flag for users its rule matches and leaves everyone
+    /// else to the next strategy.
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        self.is_enabled(flag_name, context).then_some(true)
+    }
+}
+
+/// The validation rule that checks every flag's targeting rule compiles.
+///
+/// It lives in the `config` crate and is part of `Validator::default()`, so
+/// mistakes are reported when the configuration is loaded rather than when a
+/// `RuleEvaluator` is built.
+pub use config::validation::FlagRules;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(properties: &[(&str, &str)]) -> EvaluationContext {
+        EvaluationContext {
+            properties: properties
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn check(operator: Operator, value: &str) -> bool {
+        let rule = CompiledRule::compile(&Rule::condition("attr", operator)).unwrap();
+        rule.matches(&context(&[("attr", value)]))
+    }
+
+    #[test]
+    fn test_operators() {
+        let strings = |values: &[&str]| values.iter().map(|v| v.to_string()).collect();
+        assert!(check(Operator::Equals("eu".to_string()), "eu"));
+        assert!(!check(Operator::Equals("eu".to_string()), "EU"));
+        assert!(check(Operator::In(strings(&["pro", "enterprise"])), "pro"));
+        assert!(!check(
+            Operator::In(strings(&["pro", "enterprise"])),
+            "free"
+        ));
+        assert!(check(Operator::Matches("^acme-".to_string()), "acme-eu"));
+        assert!(!check(Operator::Matches("^acme-".to_string()), "not-acme"));
+        assert!(check(Operator::Version(">=2.1, <3".to_string()), "2.4.0"));
+        assert!(!check(Operator::Version(">=2.1, <3".to_string()), "3.0.0"));
+        assert!(!check(Operator::Version(">=2.1".to_string()), "latest"));
+
+        let range = |min, max| Operator::Range { min, max };
+        assert!(check(range(Some(10.0), Some(100.0)), "10"));
+        assert!(check(range(Some(10.0), None), "250.5"));
+        assert!(!check(range(None, Some(100.0)), "101"));
+        assert!(!check(range(None, Some(100.0)), "many"));
+        assert!(!check(range(Some(10.0), Some(100.0)), "NaN"));
+        assert!(!check(range(Some(10.0), None), "inf"));
+
+        let after = || Operator::After("2025-01-01".to_string());
+        assert!(check(after(), "2025-01-02"));
+        assert!(check(after(), "2025-01-01T00:00:01Z"));
+        assert!(!check(after(), "2025-01-01"));
+        assert!(!check(after(), "2025-01-01T01:00:00+02:00"));
+        assert!(!check(after(), "yesterday"));
+    }
+
+    #[test]
+    fn test_combines_rules() {
+        let rule = CompiledRule::compile(&Rule::Any(vec![
+            Rule::condition("user_segment", Operator::Equals("staff".to_string())),
+            Rule::All(vec![
+                Rule::condition("plan", Operator::Equals("pro".to_string())),
+                Rule::condition("region", Operator::Equals("eu".to_string())),
+            ]),
+        ]))
+        .unwrap();
+
+        assert!(rule.matches(&context(&[("plan", "pro"), ("region", "eu")])));
+        assert!(!rule.matches(&context(&[("plan", "pro"), ("region", "us")])));
+        assert!(!rule.matches(&context(&[("plan", "pro")])));
+        assert!(rule.matches(&EvaluationContext {
+            user_segment: Some("staff".to_string()),
+            ..Default::default()
+        }));
+
+        assert!(CompiledRule::compile(&Rule::All(vec![]))
+            .unwrap()
+            .matches(&context(&[])));
+        assert!(!CompiledRule::compile(&Rule::Any(vec![]))
+            .unwrap()
+            .matches(&context(&[])));
+    }
+
+    #[test]
+    fn test_reports_invalid_rules() {
+        let rules = HashMap::from([(
+            "beta".to_string(),
+            Rule::All(vec![Rule::condition(
+                "app_version",
+                Operator::Version("~>2".to_string()),
+            )]),
+        )]);
+        match RuleEvaluator::new(rules) {
+            Err(RuleError::Flag { flag, source }) => {
+                assert_eq!(flag, "beta");
+                assert!(matches!(*source, RuleError::Version { .. }));
+            }
+            other => panic!("expected a version error, got {:?}", other.err()),
+        }
+
+        for operator in [
+            Operator::Matches("(".to_string()),
+            Operator::After("01/02/2025".to_string()),
+            Operator::Range {
+                min: Some(5.0),
+                max: Some(1.0),
+            },
+            Operator::Range {
+                min: Some(f64::NAN),
+                max: None,
+            },
+            Operator::Range {
+                min: None,
+                max: Some(f64::INFINITY),
+            },
+        ] {
+            assert!(CompiledRule::compile(&Rule::condition("attr", operator)).is_err());
+        }
+    }
+}
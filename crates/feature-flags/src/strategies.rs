@@ -1,5 +1,7 @@
 use crate::evaluator::{EvaluationContext, FeatureFlagEvaluator};
-use std::collections::HashMap;
+use crate::rules::{RuleError, RuleEvaluator};
+use config::flag::FlagDefinition;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
 use siphasher::sip::SipHasher;
 
@@ -15,6 +17,26 @@ impl PercentageRolloutEvaluator {
     pub fn new(percentages: HashMap<String, f32>) -> Self {
         Self { percentages }
     }
+
+    /// Builds the percentages from flag definitions, such as
+    /// `AppConfig::feature_flags`.
+    ///
+    /// Disabled flags are rolled out to nobody and enabled flags without a
+    /// `rollout` to everyone.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Self {
+        let percentages = flags
+            .iter()
+            .map(|(name, flag)| {
+                let percentage = match (flag.is_enabled(), flag.rollout) {
+                    (false, _) => 0.0,
+                    (true, Some(rollout)) => rollout as f32,
+                    (true, None) => 1.0,
+                };
+                (name.clone(), percentage)
+            })
+            .collect();
+        Self::new(percentages)
+    }
 }
 
 impl FeatureFlagEvaluator for PercentageRolloutEvaluator {
@@ -32,16 +54,29 @@ impl FeatureFlagEvaluator for PercentageRolloutEvaluator {
             None => return false, // No user ID, no percentage rollout
         };
 
-        let mut hasher = SipHasher::new();
-        flag_name.hash(&mut hasher);
-        user_id.hash(&mut hasher);
-        let hash_result = hasher.finish();
-
-        // Use the hash to determine if the user is in the percentage.
-        // We can take the hash modulo 100 to get a value from 0-99.
-        let rollout_value = (hash_result % 100) as f32;
+        // Use the bucket to determine if the user is in the percentage.
+        let rollout_value = bucket(flag_name, user_id, 100) as f32;
         rollout_value < (percentage * 100.0)
     }
+
+    /// Decides only the flags it has a percentage for.
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        self.percentages
+            .contains_key(flag_name)
+            .then(|| self.is_enabled(flag_name, context))
+    }
+}
+
+/// Returns the user's bucket for `key`, usually a flag name, from `0` to
+/// `buckets - 1`.
+///
+/// The bucket depends only on the key and user ID, so a user stays in the
+/// same bucket across evaluations and processes.
+pub(crate) fn bucket(key: &str, user_id: &str, buckets: u64) -> u64 {
+    let mut hasher = SipHasher::new();
+    key.hash(&mut hasher);
+    user_id.hash(&mut hasher);
+    hasher.finish() % buckets
 }
 
 /// An evaluator that enables features for specific user segments.
@@ -54,6 +89,18 @@ impl UserSegmentEvaluator {
     pub fn new(segments: HashMap<String, Vec<String>>) -> Self {
         Self { segments }
     }
+
+    /// Builds the segments from flag definitions, such as
+    /// `AppConfig::feature_flags`. Only enabled flags that list `segments`
+    /// are included.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Self {
+        let segments = flags
+            .iter()
+            .filter(|(_, flag)| flag.is_enabled() && !flag.segments.is_empty())
+            .map(|(name, flag)| (name.clone(), flag.segments.clone()))
+            .collect();
+        Self::new(segments)
+    }
 }
 
 impl FeatureFlagEvaluator for UserSegmentEvaluator {
@@ -65,4 +112,167 @@ impl FeatureFlagEvaluator for UserSegmentEvaluator {
         }
         false
     }
-} 
\ No newline at end of file
+
+    /// Enables the flag for users in one of its segments and leaves
+    /// everyone else to the next strategy.
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        self.is_enabled(flag_name, context).then_some(true)
+    }
+}
+
+/// An evaluator that turns flags off for everyone, regardless of any other
+/// strategy.
+///
+/// Put it first in a `CompositeEvaluator`: it decides killed flags and
+/// leaves every other flag to the strategies after it.
+pub struct KillSwitchEvaluator {
+    killed: HashSet<String>,
+}
+
+impl KillSwitchEvaluator {
+    pub fn new(killed: HashSet<String>) -> Self {
+        Self { killed }
+    }
+
+    /// Kills every disabled flag in `flags`, such as
+    /// `AppConfig::feature_flags`.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Self {
+        let killed = flags
+            .iter()
+            .filter(|(_, flag)| !flag.is_enabled())
+            .map(|(name, _)| name.clone())
+            .collect();
+        Self::new(killed)
+    }
+}
+
+impl FeatureFlagEvaluator for KillSwitchEvaluator {
+    /// Returns `false` for killed flags and `true` for all others.
+    fn is_enabled(&self, flag_name: &str, _context: &EvaluationContext) -> bool {
+        !self.killed.contains(flag_name)
+    }
+
+    fn decide(&self, flag_name: &str, _context: &EvaluationContext) -> Option<bool> {
+        self.killed.contains(flag_name).then_some(false)
+    }
+}
+
+/// An evaluator that enables features for specific user IDs.
+pub struct AllowlistEvaluator {
+    /// A map of feature flags to the user IDs they are enabled for.
+    users: HashMap<String, HashSet<String>>,
+}
+
+impl AllowlistEvaluator {
+    pub fn new(users: HashMap<String, HashSet<String>>) -> Self {
+        Self { users }
+    }
+}
+
+impl FeatureFlagEvaluator for AllowlistEvaluator {
+    fn is_enabled(&self, flag_name: &str, context: &EvaluationContext) -> bool {
+        match (self.users.get(flag_name), &context.user_id) {
+            (Some(users), Some(user_id)) => users.contains(user_id),
+            _ => false,
+        }
+    }
+
+    /// Enables the flag for listed users and leaves everyone else to the
+    /// next strategy.
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        self.is_enabled(flag_name, context).then_some(true)
+    }
+}
+
+/// An evaluator with a fixed state per flag, used as the last strategy of a
+/// `CompositeEvaluator`.
+pub struct StaticEvaluator {
+    flags: HashMap<String, bool>,
+}
+
+impl StaticEvaluator {
+    pub fn new(flags: HashMap<String, bool>) -> Self {
+        Self { flags }
+    }
+
+    /// Builds the states from flag definitions, such as
+    /// `AppConfig::feature_flags`: each flag is on if it is enabled.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Self {
+        let flags = flags
+            .iter()
+            .map(|(name, flag)| (name.clone(), flag.is_enabled()))
+            .collect();
+        Self::new(flags)
+    }
+}
+
+impl FeatureFlagEvaluator for StaticEvaluator {
+    fn is_enabled(&self, flag_name: &str, _context: &EvaluationContext) -> bool {
+        self.flags.get(flag_name).copied().unwrap_or(false)
+    }
+
+    /// Decides only the flags it has a state for.
+    fn decide(&self, flag_name: &str, _context: &EvaluationContext) -> Option<bool> {
+        self.flags.get(flag_name).copied()
+    }
+}
+
+/// An evaluator that limits flags to their audience: the users in one of
+/// the flag's `segments` who also match its `rule`.
+///
+/// In a `CompositeEvaluator` it turns a targeted flag off for everyone
+/// outside its audience and leaves the rest to the next strategy, such as a
+/// percentage rollout. Flags without `segments` or a `rule` are left to the
+/// next strategy for everyone.
+pub struct TargetingEvaluator {
+    segments: UserSegmentEvaluator,
+    rules: RuleEvaluator,
+    targeted: HashMap<String, (bool, bool)>,
+}
+
+impl TargetingEvaluator {
+    /// Builds the audiences from flag definitions, such as
+    /// `AppConfig::feature_flags`, whether the flags are enabled or not.
+    ///
+    /// Fails if a flag's rule does not compile.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Result<Self, RuleError> {
+        let segments = flags
+            .iter()
+            .filter(|(_, flag)| !flag.segments.is_empty())
+            .map(|(name, flag)| (name.clone(), flag.segments.clone()))
+            .collect();
+        let rules = flags
+            .iter()
+            .filter_map(|(name, flag)| Some((name.clone(), flag.rule.clone()?)))
+            .collect();
+        let targeted = flags
+            .iter()
+            .map(|(name, flag)| {
+                let by = (!flag.segments.is_empty(), flag.rule.is_some());
+                (name.clone(), by)
+            })
+            .filter(|(_, (segments, rule))| *segments || *rule)
+            .collect();
+        Ok(Self {
+            segments: UserSegmentEvaluator::new(segments),
+            rules: RuleEvaluator::new(rules)?,
+            targeted,
+        })
+    }
+}
+
+impl FeatureFlagEvaluator for TargetingEvaluator {
+    /// Returns `false` for users outside a targeted flag's audience and
+    /// `true` otherwise.
+    fn is_enabled(&self, flag_name: &str, context: &EvaluationContext) -> bool {
+        let Some(&(segments, rule)) = self.targeted.get(flag_name) else {
+            return true;
+        };
+        (!segments || self.segments.is_enabled(flag_name, context))
+            && (!rule || self.rules.is_enabled(flag_name, context))
+    }
+
+    fn decide(&self, flag_name: &str, context: &EvaluationContext) -> Option<bool> {
+        (!self.is_enabled(flag_name, context)).then_some(false)
+    }
+}
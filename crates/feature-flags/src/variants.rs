@@ -0,0 +1,144 @@
+//! Multivariate flags, which assign each user one of several values.
+
+use crate::{evaluator::EvaluationContext, strategies::bucket};
+use config::flag::{FlagDefinition, Variant};
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+
+/// Assigns users a variant of multivariate flags, in proportion to the
+/// variants' weights.
+///
+/// Assignment is sticky: it only depends on the flag name and
+/// `EvaluationContext::user_id`, using the same hashing as
+/// `PercentageRolloutEvaluator` but independent of it, so a flag can be
+/// rolled out to some users and split into variants among them. Users
+/// without an ID are not assigned a variant.
+pub struct VariantEvaluator {
+    variants: HashMap<String, Vec<Variant>>,
+}
+
+impl VariantEvaluator {
+    pub fn new(variants: HashMap<String, Vec<Variant>>) -> Self {
+        Self { variants }
+    }
+
+    /// Builds the variants from flag definitions, such as
+    /// `AppConfig::feature_flags`. Every flag that lists `variants` is
+    /// included, whether it is enabled or not; deciding whether the user
+    /// gets a variant at all is left to the caller, such as
+    /// `FeatureFlagManager::get_variant`.
+    pub fn from_flags(flags: &HashMap<String, FlagDefinition>) -> Self {
+        let variants = flags
+            .iter()
+            .filter(|(_, flag)| !flag.variants.is_empty())
+            .map(|(name, flag)| (name.clone(), flag.variants.clone()))
+            .collect();
+        Self::new(variants)
+    }
+
+    /// Returns the value of the variant assigned to the user, or `None` if
+    /// the flag has no variants or the context no user ID.
+    pub fn variant(
+        &self,
+        flag_name: &str,
+        context: &EvaluationContext,
+    ) -> Option<&serde_json::Value> {
+        let variants = self.variants.get(flag_name)?;
+        let user_id = context.user_id.as_deref()?;
+        let total: u64 = variants
+            .iter()
+            .map(|variant| u64::from(variant.weight))
+            .sum();
+        if total == 0 {
+            return None;
+        }
+
+        // Salt the key so that the variant does not follow the rollout bucket.
+        let mut position = bucket(&format!("{}/variant", flag_name), user_id, total);
+        variants.iter().find_map(|variant| {
+            let weight = u64::from(variant.weight);
+            if position < weight {
+                Some(&variant.value)
+            } else {
+                position -= weight;
+                None
+            }
+        })
+    }
+
+    /// Returns the assigned variant as a `T`, such as a `String`, a number
+    /// or a type deserializing a table.
+    ///
+    /// Returns `None` if no variant is assigned or its value is not a `T`.
+    pub fn get_variant<T: DeserializeOwned>(
+        &self,
+        flag_name: &str,
+        context: &EvaluationContext,
+    ) -> Option<T> {
+        let value = self.variant(flag_name, context)?;
+        serde_json::from_value(value.clone()).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> EvaluationContext {
+        EvaluationContext {
+            user_id: Some(id.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_assignment_follows_weights() {
+        let evaluator = VariantEvaluator::new(HashMap::from([(
+            "checkout_layout".to_string(),
+            vec![
+                Variant::new("single_page", 3),
+                Variant::new("multi_step", 1),
+                Variant::new("never", 0),
+            ],
+        )]));
+
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for i in 0..4000 {
+            let variant: String = evaluator
+                .get_variant("checkout_layout", &user(&format!("user{}", i)))
+                .unwrap();
+            *counts.entry(variant).or_default() += 1;
+        }
+        assert!(
+            (2800..3200).contains(&counts["single_page"]),
+            "{:?}",
+            counts
+        );
+        assert!((800..1200).contains(&counts["multi_step"]), "{:?}", counts);
+        assert!(!counts.contains_key("never"));
+    }
+
+    #[test]
+    fn test_assignment_is_sticky() {
+        let evaluator = VariantEvaluator::new(HashMap::from([(
+            "checkout_layout".to_string(),
+            vec![Variant::new("a", 1), Variant::new("b", 1)],
+        )]));
+
+        let first = evaluator
+            .variant("checkout_layout", &user("user42"))
+            .cloned();
+        for _ in 0..10 {
+            assert_eq!(
+                evaluator
+                    .variant("checkout_layout", &user("user42"))
+                    .cloned(),
+                first
+            );
+        }
+        assert!(evaluator
+            .variant("checkout_layout", &EvaluationContext::default())
+            .is_none());
+        assert!(evaluator.variant("unknown", &user("user42")).is_none());
+    }
+}
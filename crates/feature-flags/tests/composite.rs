@@ -0,0 +1,184 @@
+use config::{builder::AppConfigBuilder, flag::FlagDefinition, rule::Operator, rule::Rule};
+use feature_flags::composite::{CompositeEvaluator, Evaluation};
+use feature_flags::evaluator::{EvaluationContext, FeatureFlagEvaluator};
+use feature_flags::strategies::{AllowlistEvaluator, KillSwitchEvaluator, StaticEvaluator};
+use std::collections::{HashMap, HashSet};
+
+fn user(id: &str, segment: Option<&str>) -> EvaluationContext {
+    EvaluationContext {
+        user_id: Some(id.to_string()),
+        user_segment: segment.map(str::to_string),
+        ..Default::default()
+    }
+}
+
+fn decided(enabled: bool, by: &str) -> Evaluation {
+    Evaluation {
+        enabled,
+        decided_by: Some(by.to_string()),
+    }
+}
+
+#[test]
+fn test_composite_from_flag_definitions() {
+    let config = AppConfigBuilder::new()
+        .feature_flag("dark_mode", true)
+        .feature_flag("legacy", false)
+        .feature_flag(
+            "new_ui",
+            FlagDefinition {
+                enabled: Some(true),
+                rollout: Some(0.0),
+                segments: vec!["beta_testers".to_string()],
+                ..Default::default()
+            },
+        )
+        .feature_flag(
+            "reports",
+            FlagDefinition {
+                enabled: Some(true),
+                rule: Some(Rule::condition(
+                    "user_id",
+                    Operator::In(vec!["alice".to_string()]),
+                )),
+                ..Default::default()
+            },
+        )
+        .try_build()
+        .unwrap();
+    let evaluator = CompositeEvaluator::from_flags(&config.feature_flags).unwrap();
+    assert_eq!(
+        evaluator.strategy_names(),
+        vec!["kill-switch", "targeting", "rollout", "default"]
+    );
+
+    let beta = user("bob", Some("beta_testers"));
+    let other = user("carol", None);
+    assert_eq!(
+        evaluator.evaluate("dark_mode", &other),
+        decided(true, "default")
+    );
+    assert_eq!(
+        evaluator.evaluate("legacy", &beta),
+        decided(false, "kill-switch")
+    );
+    assert_eq!(
+        evaluator.evaluate("new_ui", &beta),
+        decided(false, "rollout")
+    );
+    assert_eq!(
+        evaluator.evaluate("new_ui", &other),
+        decided(false, "targeting")
+    );
+    assert_eq!(
+        evaluator.evaluate("reports", &user("alice", None)),
+        decided(true, "default")
+    );
+    assert_eq!(
+        evaluator.evaluate("reports", &other),
+        decided(false, "targeting")
+    );
+    assert_eq!(
+        evaluator.evaluate("not_configured", &other),
+        Evaluation {
+            enabled: false,
+            decided_by: None,
+        }
+    );
+}
+
+#[test]
+fn test_composite_targeting_narrows_rollout() {
+    let config = AppConfigBuilder::new()
+        .feature_flag(
+            "new_ui",
+            FlagDefinition {
+                rollout: Some(0.25),
+                segments: vec!["beta".to_string()],
+                ..Default::default()
+            },
+        )
+        .feature_flag(
+            "reports",
+            FlagDefinition {
+                segments: vec!["beta".to_string()],
+                rule: Some(Rule::condition(
+                    "region",
+                    Operator::Equals("eu".to_string()),
+                )),
+                ..Default::default()
+            },
+        )
+        .build();
+    let evaluator = CompositeEvaluator::from_flags(&config.feature_flags).unwrap();
+
+    let enabled_for = |segment: Option<&str>| {
+        (0..1000)
+            .filter(|i| evaluator.is_enabled("new_ui", &user(&format!("user{}", i), segment)))
+            .count()
+    };
+    let beta = enabled_for(Some("beta"));
+    assert!((180..320).contains(&beta), "{} beta users enabled", beta);
+    assert_eq!(enabled_for(None), 0);
+    assert_eq!(enabled_for(Some("internal")), 0);
+
+    let mut eu_beta = user("alice", Some("beta"));
+    eu_beta
+        .properties
+        .insert("region".to_string(), "eu".to_string());
+    let mut eu_other = user("bob", None);
+    eu_other
+        .properties
+        .insert("region".to_string(), "eu".to_string());
+    assert_eq!(
+        evaluator.evaluate("reports", &eu_beta),
+        decided(true, "default")
+    );
+    assert_eq!(
+        evaluator.evaluate("reports", &eu_other),
+        decided(false, "targeting")
+    );
+    assert_eq!(
+        evaluator.evaluate("reports", &user("carol", Some("beta"))),
+        decided(false, "targeting")
+    );
+}
+
+#[test]
+fn test_composite_precedence_and_selection() {
+    let allowlist = || {
+        AllowlistEvaluator::new(HashMap::from([
+            ("new_ui".to_string(), HashSet::from(["alice".to_string()])),
+            ("search".to_string(), HashSet::from(["alice".to_string()])),
+        ]))
+    };
+    let evaluator = CompositeEvaluator::new()
+        .with_strategy(
+            "kill-switch",
+            KillSwitchEvaluator::new(HashSet::from(["search".to_string()])),
+        )
+        .with_strategy("allowlist", allowlist())
+        .with_strategy(
+            "default",
+            StaticEvaluator::new(HashMap::from([
+                ("new_ui".to_string(), false),
+                ("search".to_string(), true),
+            ])),
+        )
+        .with_flag_strategies("new_ui", ["default"]);
+
+    let alice = user("alice", None);
+    assert_eq!(
+        evaluator.evaluate("search", &alice),
+        decided(false, "kill-switch")
+    );
+    assert_eq!(
+        evaluator.evaluate("new_ui", &alice),
+        decided(false, "default")
+    );
+    assert!(!evaluator.is_enabled("new_ui", &alice));
+
+    let nested = CompositeEvaluator::new().with_strategy("chain", evaluator);
+    assert_eq!(nested.evaluate("search", &alice), decided(false, "chain"));
+    assert_eq!(nested.evaluate("unknown", &alice).decided_by, None);
+}
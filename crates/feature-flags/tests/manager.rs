@@ -0,0 +1,152 @@
+use config::{
+    builder::AppConfigBuilder,
+    flag::{FlagDefinition, Variant},
+};
+use feature_flags::evaluator::EvaluationContext;
+use feature_flags::manager::FeatureFlagManager;
+use feature_flags::strategies::PercentageRolloutEvaluator;
+use std::collections::HashMap;
+
+#[test]
+fn test_manager_honors_stored_state() {
+    let evaluator = PercentageRolloutEvaluator::new(HashMap::from([
+        ("dark_mode".to_string(), 1.0),
+        ("search".to_string(), 1.0),
+    ]));
+    let manager =
+        FeatureFlagManager::new(evaluator, HashMap::from([("dark_mode".to_string(), true)]));
+    let context = EvaluationContext::default();
+
+    assert!(manager.is_enabled("dark_mode", &context));
+    assert!(manager.is_enabled("search", &context));
+
+    manager.update_flag("dark_mode".to_string(), false);
+    assert!(!manager.is_enabled("dark_mode", &context));
+    assert_eq!(manager.flag_state("dark_mode"), Some(false));
+
+    manager.update_flag("dark_mode".to_string(), true);
+    assert!(manager.is_enabled("dark_mode", &context));
+}
+
+#[test]
+fn test_manager_overrides_win() {
+    let manager = FeatureFlagManager::new(
+        PercentageRolloutEvaluator::new(HashMap::new()),
+        HashMap::from([("beta".to_string(), false)]),
+    );
+    let context = EvaluationContext::default();
+
+    manager.set_override("beta", true);
+    manager.set_override("unknown", true);
+    assert!(manager.is_enabled("beta", &context));
+    assert!(manager.is_enabled("unknown", &context));
+
+    assert_eq!(manager.clear_override("beta"), Some(true));
+    assert!(!manager.is_enabled("beta", &context));
+    assert_eq!(
+        manager.overrides(),
+        HashMap::from([("unknown".to_string(), true)])
+    );
+}
+
+#[test]
+fn test_manager_from_config() {
+    let config = AppConfigBuilder::new()
+        .feature_flag("dark_mode", true)
+        .feature_flag("legacy", false)
+        .feature_flag(
+            "new_ui",
+            FlagDefinition {
+                enabled: Some(true),
+                segments: vec!["beta_testers".to_string()],
+                ..Default::default()
+            },
+        )
+        .try_build()
+        .unwrap();
+    let manager = FeatureFlagManager::from_config(&config).unwrap();
+    let beta = EvaluationContext {
+        user_segment: Some("beta_testers".to_string()),
+        ..Default::default()
+    };
+    let other = EvaluationContext::default();
+
+    assert!(manager.is_enabled("dark_mode", &other));
+    assert!(!manager.is_enabled("legacy", &beta));
+    assert!(manager.is_enabled("new_ui", &beta));
+    assert!(!manager.is_enabled("new_ui", &other));
+    assert!(!manager.is_enabled("not_configured", &other));
+
+    manager.update_flag("dark_mode".to_string(), false);
+    assert!(!manager.is_enabled("dark_mode", &other));
+    manager.update_flag("legacy".to_string(), true);
+    assert!(manager.is_enabled("legacy", &other));
+    manager.update_flag("new_ui".to_string(), false);
+    manager.set_override("new_ui", true);
+    assert!(manager.is_enabled("new_ui", &other));
+}
+
+#[test]
+fn test_manager_variants() {
+    let config = AppConfigBuilder::new()
+        .feature_flag(
+            "checkout_layout",
+            FlagDefinition {
+                enabled: Some(true),
+                variants: vec![
+                    Variant::new("single_page", 1),
+                    Variant::new("multi_step", 1),
+                ],
+                ..Default::default()
+            },
+        )
+        .feature_flag(
+            "search_ranking",
+            FlagDefinition {
+                enabled: Some(false),
+                variants: vec![Variant::new("bm25", 1), Variant::new("semantic", 1)],
+                ..Default::default()
+            },
+        )
+        .feature_flag(
+            "pricing",
+            FlagDefinition {
+                enabled: Some(true),
+                variants: vec![Variant::new(serde_json::json!({ "discount": 10 }), 1)],
+                ..Default::default()
+            },
+        )
+        .try_build()
+        .unwrap();
+    let manager = FeatureFlagManager::from_config(&config).unwrap();
+    let user = EvaluationContext {
+        user_id: Some("user123".to_string()),
+        ..Default::default()
+    };
+
+    let layout = manager
+        .get_variant::<String>("checkout_layout", &user)
+        .unwrap();
+    assert!(layout == "single_page" || layout == "multi_step");
+    assert_eq!(
+        manager.get_variant::<String>("checkout_layout", &user),
+        Some(layout)
+    );
+    assert_eq!(
+        manager.get_variant::<HashMap<String, u32>>("pricing", &user),
+        Some(HashMap::from([("discount".to_string(), 10)]))
+    );
+    assert_eq!(manager.get_variant::<u32>("pricing", &user), None);
+
+    manager.update_flag("checkout_layout".to_string(), false);
+    assert_eq!(
+        manager.get_variant::<String>("checkout_layout", &user),
+        None
+    );
+
+    assert_eq!(manager.get_variant::<String>("search_ranking", &user), None);
+    manager.set_override("search_ranking", true);
+    assert!(manager
+        .get_variant::<String>("search_ranking", &user)
+        .is_some());
+}
@@ -0,0 +1,69 @@
+use config::{
+    errors::ConfigError, loader::FileConfigurationProvider, traits::ConfigurationProvider,
+};
+use feature_flags::evaluator::{EvaluationContext, FeatureFlagEvaluator};
+use feature_flags::rules::RuleEvaluator;
+use std::collections::HashMap;
+use test_utils::mock_fs::create_temp_file_with_extension;
+
+fn tenant(plan: &str, region: &str) -> EvaluationContext {
+    EvaluationContext {
+        properties: HashMap::from([
+            ("plan".to_string(), plan.to_string()),
+            ("region".to_string(), region.to_string()),
+        ]),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_rule_evaluator_from_config() {
+    let file = create_temp_file_with_extension(
+        ".toml",
+        r#"
+        [feature_flags.multi_currency]
+        rule.all = [
+            { attribute = "plan", in = ["pro", "enterprise"] },
+            { attribute = "region", equals = "eu" },
+        ]
+
+        [feature_flags.retired]
+        enabled = false
+        rule = { attribute = "region", equals = "eu" }
+        "#,
+    );
+    let provider = FileConfigurationProvider::new(file.path());
+    let config = provider.load().unwrap().unwrap();
+    provider.validate(&config).unwrap();
+
+    let evaluator = RuleEvaluator::from_flags(&config.feature_flags).unwrap();
+    assert!(evaluator.is_enabled("multi_currency", &tenant("enterprise", "eu")));
+    assert!(!evaluator.is_enabled("multi_currency", &tenant("free", "eu")));
+    assert!(!evaluator.is_enabled("multi_currency", &tenant("pro", "us")));
+    assert!(!evaluator.is_enabled("retired", &tenant("pro", "eu")));
+    assert!(!evaluator.is_enabled("not_configured", &tenant("pro", "eu")));
+}
+
+#[test]
+fn test_flag_rules_validation() {
+    let file = create_temp_file_with_extension(
+        ".toml",
+        r#"
+        [feature_flags.beta]
+        rule = { attribute = "email", matches = "[a-z" }
+        "#,
+    );
+    let provider = FileConfigurationProvider::new(file.path());
+    let config = provider.load().unwrap().unwrap();
+
+    match provider.validate(&config) {
+        Err(ConfigError::Validation(errors)) => {
+            let failures = errors.failures();
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].rule, "flag-rules");
+            assert_eq!(failures[0].field, "feature_flags.beta.rule");
+            assert!(failures[0].message.contains("invalid regular expression"));
+        }
+        other => panic!("expected a validation error, got {:?}", other),
+    }
+}
@@ -1,3 +1,6 @@
+#![allow(clippy::field_reassign_with_default)]
+
+use config::{builder::AppConfigBuilder, flag::FlagDefinition};
 use feature_flags::evaluator::{EvaluationContext, FeatureFlagEvaluator};
 use feature_flags::strategies::{PercentageRolloutEvaluator, UserSegmentEvaluator};
 use std::collections::HashMap;
@@ -79,4 +82,35 @@ fn test_percentage_rollout_consistency() {
     for _ in 0..100 {
         assert_eq!(first_result, evaluator.is_enabled("consistent_feature", &context));
     }
-} 
\ No newline at end of file
+}
+
+#[test]
+fn test_evaluators_from_flag_definitions() {
+    let config = AppConfigBuilder::new()
+        .feature_flag("dark_mode", true)
+        .feature_flag("legacy", false)
+        .feature_flag(
+            "new_ui",
+            FlagDefinition {
+                enabled: Some(true),
+                rollout: Some(0.0),
+                segments: vec!["beta_testers".to_string()],
+                ..Default::default()
+            },
+        )
+        .build();
+    let context = EvaluationContext {
+        user_id: Some("user123".to_string()),
+        user_segment: Some("beta_testers".to_string()),
+        ..Default::default()
+    };
+
+    let rollout = PercentageRolloutEvaluator::from_flags(&config.feature_flags);
+    assert!(rollout.is_enabled("dark_mode", &context));
+    assert!(!rollout.is_enabled("legacy", &context));
+    assert!(!rollout.is_enabled("new_ui", &context));
+
+    let segments = UserSegmentEvaluator::from_flags(&config.feature_flags);
+    assert!(segments.is_enabled("new_ui", &context));
+    assert!(!segments.is_enabled("dark_mode", &context));
+}
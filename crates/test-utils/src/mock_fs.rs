@@ -1,6 +1,7 @@
 use std::fs::{self, File};
 use std::io::{self, Write};
 use std::path::Path;
+use tempfile::NamedTempFile;
 
 /// Creates a file with the given content at the specified path.
 ///
@@ -19,6 +20,37 @@ pub fn create_file_with_content(path: &Path, content: &str) -> io::Result<()> {
     Ok(())
 }
 
+/// Creates a temporary file holding `content`.
+///
+/// The file is deleted when the returned handle is dropped, so keep it
+/// alive for as long as the test reads from its path.
+///
+/// # Panics
+///
+/// Panics if the file cannot be created or written.
+pub fn create_temp_config_file(content: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
+/// Creates a temporary file holding `content` whose name ends in `extension`.
+///
+/// Use this over [`create_temp_config_file`] when the code under test picks
+/// a format from the file extension.
+///
+/// # Panics
+///
+/// Panics if the file cannot be created or written.
+pub fn create_temp_file_with_extension(extension: &str, content: &str) -> NamedTempFile {
+    let mut file = tempfile::Builder::new()
+        .suffix(extension)
+        .tempfile()
+        .unwrap();
+    file.write_all(content.as_bytes()).unwrap();
+    file
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -36,4 +68,20 @@ mod tests {
         let read_content = fs::read_to_string(&file_path).unwrap();
         assert_eq!(read_content, content);
     }
+
+    #[test]
+    fn test_create_temp_config_file() {
+        let file = create_temp_config_file("log_level = \"debug\"\n");
+
+        let read_content = fs::read_to_string(file.path()).unwrap();
+        assert_eq!(read_content, "log_level = \"debug\"\n");
+    }
+
+    #[test]
+    fn test_create_temp_file_with_extension() {
+        let file = create_temp_file_with_extension(".json", "{}");
+
+        assert_eq!(file.path().extension().unwrap(), "json");
+        assert_eq!(fs::read_to_string(file.path()).unwrap(), "{}");
+    }
 } 
\ No newline at end of file